@@ -1,29 +1,114 @@
+use std::env;
+use std::fmt;
 use std::fs;
 
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Command {
+    Forward(u32),
+    Down(u32),
+    Up(u32),
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Command::Forward(v) => write!(f, "forward {}", v),
+            Command::Down(v) => write!(f, "down {}", v),
+            Command::Up(v) => write!(f, "up {}", v),
+        }
+    }
+}
+
+fn parse_command(line: &str) -> Command {
+    let mut parsed = line.split(' ');
+    let instruction : &str = parsed.next().unwrap();
+    let value : u32 = parsed.next().unwrap().parse().unwrap();
+
+    if instruction == "forward" {
+        Command::Forward(value)
+    } else if instruction == "down" {
+        Command::Down(value)
+    } else if instruction == "up" {
+        Command::Up(value)
+    } else {
+        panic!("unknown instruction '{}'", instruction);
+    }
+}
+
+fn parse_commands(text: &str) -> Vec<Command> {
+    text.split('\n').filter(|l| ! l.is_empty()).map(parse_command).collect()
+}
+
+fn execute_commands(commands: &[Command]) -> (u32, u32) {
+    let mut depth : u32 = 0;
+    let mut dist : u32 = 0;
+    for command in commands {
+        match *command {
+            Command::Forward(value) => dist += value,
+            Command::Down(value) => depth += value,
+            Command::Up(value) => depth -= value,
+        }
+    }
+    (dist, depth)
+}
+
+fn synthesize_course(dist: u32, depth: u32) -> Vec<Command> {
+    let mut commands : Vec<Command> = Vec::new();
+    if dist > 0 {
+        commands.push(Command::Forward(dist));
+    }
+    if depth > 0 {
+        commands.push(Command::Down(depth));
+    }
+    commands
+}
+
+fn synthesize_and_verify(dist: u32, depth: u32) -> Vec<Command> {
+    let commands = synthesize_course(dist, depth);
+    assert_eq!(execute_commands(&commands), (dist, depth));
+    commands
+}
+
 fn main()  {
-        let contents = fs::read_to_string("input.txt").expect("Input file read failed");
-        let lines = contents.split("\n");
-        let mut depth : u32 = 0;
-        let mut dist : u32 = 0;
-        for line in lines {
-            if line.is_empty() {
-                continue;
-            }
-            let mut parsed = line.split(" ");
-            let instruction : &str = parsed.next().unwrap();
-            let value : u32 = parsed.next().unwrap().parse().unwrap();
-
-            if instruction == "forward" {
-                dist += value;
-            } else if instruction == "down" {
-                depth += value;
-            } else if instruction == "up" {
-                depth -= value;
-            } else {
-                panic!("unknown instruction '{}'", instruction);
+        let args : Vec<String> = env::args().collect();
+        if args.len() == 4 && args[1] == "synthesize" {
+            let dist : u32 = args[2].parse().expect("invalid distance");
+            let depth : u32 = args[3].parse().expect("invalid depth");
+            for command in synthesize_and_verify(dist, depth) {
+                println!("{}", command);
             }
+            return;
         }
+
+        let contents = fs::read_to_string("input.txt").expect("Input file read failed");
+        let (dist, depth) = execute_commands(&parse_commands(&contents));
         println!("{}", dist);
         println!("{}", depth);
         println!("{}", dist*depth);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+#[test]
+    fn test_execute_upstream_example() {
+        let commands = parse_commands("forward 5\ndown 5\nforward 8\nup 3\ndown 8\nforward 2\n");
+        assert_eq!(execute_commands(&commands), (15, 10));
+    }
+
+#[test]
+    fn test_synthesize_course() {
+        assert_eq!(synthesize_and_verify(0, 0), vec![]);
+        assert_eq!(synthesize_and_verify(15, 0), vec![Command::Forward(15)]);
+        assert_eq!(synthesize_and_verify(0, 10), vec![Command::Down(10)]);
+        assert_eq!(synthesize_and_verify(15, 10), vec![Command::Forward(15), Command::Down(10)]);
+    }
+
+#[test]
+    fn test_synthesized_course_round_trips_as_text() {
+        let text : String = synthesize_course(15, 10).iter().map(|c| format!("{}\n", c)).collect();
+        assert_eq!(execute_commands(&parse_commands(&text)), (15, 10));
+    }
+
+}
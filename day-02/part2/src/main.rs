@@ -1,31 +1,141 @@
+use std::env;
+use std::fmt;
 use std::fs;
 
-fn main()  {
-        let contents = fs::read_to_string("input.txt").expect("Input file read failed");
-        let lines = contents.split("\n");
-        let mut aim : u32 = 0;
-        let mut depth : u32 = 0;
-        let mut dist : u32 = 0;
-        for line in lines {
-            if line.is_empty() {
-                continue;
-            }
-            let mut parsed = line.split(" ");
-            let instruction : &str = parsed.next().unwrap();
-            let value : u32 = parsed.next().unwrap().parse().unwrap();
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Command {
+    Forward(u32),
+    Down(u32),
+    Up(u32),
+}
 
-            if instruction == "forward" {
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Command::Forward(v) => write!(f, "forward {}", v),
+            Command::Down(v) => write!(f, "down {}", v),
+            Command::Up(v) => write!(f, "up {}", v),
+        }
+    }
+}
+
+fn parse_command(line: &str) -> Command {
+    let mut parsed = line.split(' ');
+    let instruction : &str = parsed.next().unwrap();
+    let value : u32 = parsed.next().unwrap().parse().unwrap();
+
+    if instruction == "forward" {
+        Command::Forward(value)
+    } else if instruction == "down" {
+        Command::Down(value)
+    } else if instruction == "up" {
+        Command::Up(value)
+    } else {
+        panic!("unknown instruction '{}'", instruction);
+    }
+}
+
+fn parse_commands(text: &str) -> Vec<Command> {
+    text.split('\n').filter(|l| ! l.is_empty()).map(parse_command).collect()
+}
+
+fn execute_commands(commands: &[Command]) -> (u32, u32) {
+    let mut aim : u32 = 0;
+    let mut depth : u32 = 0;
+    let mut dist : u32 = 0;
+    for command in commands {
+        match *command {
+            Command::Forward(value) => {
                 dist += value;
                 depth += aim*value;
-            } else if instruction == "down" {
-                aim += value;
-            } else if instruction == "up" {
-                aim -= value;
-            } else {
-                panic!("unknown instruction '{}'", instruction);
+            },
+            Command::Down(value) => aim += value,
+            Command::Up(value) => aim -= value,
+        }
+    }
+    (dist, depth)
+}
+
+fn synthesize_course(dist: u32, depth: u32) -> Result<Vec<Command>, String> {
+    if depth == 0 {
+        if dist == 0 {
+            return Ok(vec![]);
+        }
+        return Ok(vec![Command::Forward(dist)]);
+    }
+
+    // depth only grows while moving forward, so there is no way to get deeper
+    // without moving
+    if dist == 0 {
+        return Err(format!("depth {} cannot be reached without moving forward", depth));
+    }
+
+    // aim up front and move all the way in one go
+    if depth.is_multiple_of(dist) {
+        return Ok(vec![Command::Down(depth/dist), Command::Forward(dist)]);
+    }
+
+    // otherwise move at zero aim first and make the whole dive on the last unit
+    Ok(vec![Command::Forward(dist - 1), Command::Down(depth), Command::Forward(1)])
+}
+
+fn synthesize_and_verify(dist: u32, depth: u32) -> Result<Vec<Command>, String> {
+    let commands = synthesize_course(dist, depth)?;
+    assert_eq!(execute_commands(&commands), (dist, depth));
+    Ok(commands)
+}
+
+fn main()  {
+        let args : Vec<String> = env::args().collect();
+        if args.len() == 4 && args[1] == "synthesize" {
+            let dist : u32 = args[2].parse().expect("invalid distance");
+            let depth : u32 = args[3].parse().expect("invalid depth");
+            match synthesize_and_verify(dist, depth) {
+                Ok(commands) => for command in commands {
+                    println!("{}", command);
+                },
+                Err(message) => eprintln!("{}", message),
             }
+            return;
         }
+
+        let contents = fs::read_to_string("input.txt").expect("Input file read failed");
+        let (dist, depth) = execute_commands(&parse_commands(&contents));
         println!("{}", dist);
         println!("{}", depth);
         println!("{}", dist*depth);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+#[test]
+    fn test_execute_upstream_example() {
+        let commands = parse_commands("forward 5\ndown 5\nforward 8\nup 3\ndown 8\nforward 2\n");
+        assert_eq!(execute_commands(&commands), (15, 60));
+    }
+
+#[test]
+    fn test_synthesize_course() {
+        assert_eq!(synthesize_and_verify(0, 0), Ok(vec![]));
+        assert_eq!(synthesize_and_verify(15, 0), Ok(vec![Command::Forward(15)]));
+        assert_eq!(synthesize_and_verify(15, 60), Ok(vec![Command::Down(4), Command::Forward(15)]));
+        assert_eq!(synthesize_and_verify(15, 61).unwrap().len(), 3);
+        assert_eq!(synthesize_and_verify(1, 7).unwrap().len(), 2);
+        assert!(synthesize_and_verify(0, 10).is_err());
+    }
+
+#[test]
+    fn test_synthesize_course_small_targets() {
+        for dist in 0 .. 20 {
+            for depth in 0 .. 50 {
+                if dist == 0 && depth > 0 {
+                    continue;
+                }
+                synthesize_and_verify(dist, depth).unwrap();
+            }
+        }
+    }
+
+}
@@ -1,15 +1,39 @@
 use std::fs;
 
-fn main()  {
-        let contents = fs::read_to_string("input.txt").expect("Input file read failed");
-        let lines = contents.split("\n");
-        let mut ones_count : [u32; 12] = [0; 12]; 
-        let mut zeros_count : [u32; 12] = [0; 12];
+use num_bigint::BigUint;
+
+const MAX_WIDTH : usize = 128;
+
+fn validate_report(text: &str) -> Result<Vec<&str>, String> {
+    let lines : Vec<&str> = text.split('\n').filter(|l| ! l.is_empty()).collect();
+
+    if lines.is_empty() {
+        return Err("report is empty".to_string());
+    }
+
+    let width = lines[0].len();
+    if width > MAX_WIDTH {
+        return Err(format!("line 1 '{}' is wider than {} bits", lines[0], MAX_WIDTH));
+    }
+
+    for (i, line) in lines.iter().enumerate() {
+        if line.chars().any(|c| c != '0' && c != '1') {
+            return Err(format!("line {} '{}' contains characters other than '0' and '1'", i + 1, line));
+        }
+        if line.len() != width {
+            return Err(format!("line {} '{}' is {} bits wide, expected {}", i + 1, line, line.len(), width));
+        }
+    }
+
+    Ok(lines)
+}
+
+fn get_rates(lines: &[&str]) -> (u128, u128) {
+        let width = lines[0].len();
+        let mut ones_count : Vec<u32> = vec![0; width];
+        let mut zeros_count : Vec<u32> = vec![0; width];
 
         for line in lines {
-            if line.is_empty() {
-                continue;
-            }
             for (i, c) in line.chars().enumerate() {
                 if c == '1' {
                     ones_count[i] += 1;
@@ -19,19 +43,84 @@ fn main()  {
             }
         }
 
-        let mut gamma_rate : u32 = 0;
-        let mut epsilon_rate : u32 = 0;
+        let mut gamma_rate : u128 = 0;
+        let mut epsilon_rate : u128 = 0;
 
         for (&c0, c1) in zeros_count.iter().zip(ones_count) {
-            gamma_rate *= 2;
-            epsilon_rate *= 2;
+            gamma_rate <<= 1;
+            epsilon_rate <<= 1;
 
-            if c0 > c1 {
+            if c1 > c0 {
                 gamma_rate += 1;
             } else {
                 epsilon_rate += 1;
             }
         }
 
-        println!("{}", gamma_rate*epsilon_rate);
+        (gamma_rate, epsilon_rate)
+}
+
+// both rates can be up to 128 bits wide, so the product needs up to 256
+fn power_consumption(gamma_rate: u128, epsilon_rate: u128) -> BigUint {
+    BigUint::from(gamma_rate) * BigUint::from(epsilon_rate)
+}
+
+fn main()  {
+        let contents = fs::read_to_string("input.txt").expect("Input file read failed");
+        let lines = match validate_report(&contents) {
+            Ok(lines) => lines,
+            Err(message) => panic!("invalid report: {}", message),
+        };
+
+        let (gamma_rate, epsilon_rate) = get_rates(&lines);
+
+        println!("{}", power_consumption(gamma_rate, epsilon_rate));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+#[test]
+    fn test_rates_upstream_example() {
+        let text = "00100\n11110\n10110\n10111\n10101\n01111\n00111\n11100\n10000\n11001\n00010\n01010\n";
+        let lines = validate_report(text).unwrap();
+        assert_eq!(get_rates(&lines), (22, 9));
+    }
+
+#[test]
+    fn test_rates_wide_lines() {
+        let one = format!("1{}", "0".repeat(127));
+        let zero = "0".repeat(128);
+        let lines = vec![one.as_str(), one.as_str(), zero.as_str()];
+        assert_eq!(get_rates(&lines), (1 << 127, (1 << 127) - 1));
+    }
+
+#[test]
+    fn test_power_consumption_wide_lines() {
+        // a 100 bit report, whose product no longer fits in u128
+        let half = "1".repeat(50) + &"0".repeat(50);
+        let text = format!("{}\n{}\n{}\n", half, half, "0".repeat(100));
+        let lines = validate_report(&text).unwrap();
+        let (gamma_rate, epsilon_rate) = get_rates(&lines);
+        let low_bits = BigUint::from((1u64 << 50) - 1);
+        assert_eq!(power_consumption(gamma_rate, epsilon_rate), (&low_bits << 50) * &low_bits);
+
+        let one = format!("1{}", "0".repeat(127));
+        let zero = "0".repeat(128);
+        let (gamma_rate, epsilon_rate) = get_rates(&[one.as_str(), one.as_str(), zero.as_str()]);
+        let expected = (BigUint::from(1u32) << 127) * ((BigUint::from(1u32) << 127) - 1u32);
+        assert_eq!(power_consumption(gamma_rate, epsilon_rate), expected);
+        assert_eq!(power_consumption(22, 9), BigUint::from(198u32));
+    }
+
+#[test]
+    fn test_validate_report() {
+        assert!(validate_report("").is_err());
+        assert_eq!(validate_report("01\n10\n"), Ok(vec!["01", "10"]));
+        assert_eq!(validate_report("01\n102\n").unwrap_err(), "line 2 '102' contains characters other than '0' and '1'");
+        assert_eq!(validate_report("01\n101\n").unwrap_err(), "line 2 '101' is 3 bits wide, expected 2");
+        assert!(validate_report(&"1".repeat(129)).is_err());
+    }
+
 }
@@ -1,101 +1,227 @@
+use std::env;
 use std::fs;
 
-fn get_nth(string: &str, pos: usize) -> char {
-    string.chars().nth(pos).unwrap()
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Bit {
+    Zero,
+    One,
 }
 
-fn count_zeroes_at(lines: &[&str], position: usize) -> usize {
-    let mut count : usize = 0;
-    for line in lines {
-        if get_nth(line, position) == '0' {
-            count += 1;
-        } else {
-            break;
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum BitRule {
+    MostCommon { tie: Bit },
+    LeastCommon { tie: Bit },
+    Fixed(Bit),
+    Skip,
+}
+
+impl BitRule {
+    // None means both bit values are kept
+    fn select(&self, zero_count: usize, one_count: usize) -> Option<Bit> {
+        match *self {
+            BitRule::Fixed(bit) => Some(bit),
+            BitRule::Skip => None,
+            _ if zero_count == 0 => Some(Bit::One),
+            _ if one_count == 0 => Some(Bit::Zero),
+            BitRule::MostCommon{tie} | BitRule::LeastCommon{tie} if zero_count == one_count => Some(tie),
+            BitRule::MostCommon{..} => Some(if one_count > zero_count { Bit::One } else { Bit::Zero }),
+            BitRule::LeastCommon{..} => Some(if one_count < zero_count { Bit::One } else { Bit::Zero }),
         }
     }
-    count
 }
 
-fn get_o2_generator_rating(lines: &[&str]) -> u32 {
-        let mut o2_generator_rating : u32 = 0;
+#[derive(Debug, Clone)]
+struct Criteria {
+    default: BitRule,
+    rules: Vec<Option<BitRule>>,
+}
 
-        let mut slice_size = lines.len();
-        let mut slice_pos : usize = 0;
-        let mut zero_count : usize = 0;
+impl Criteria {
+    pub fn new(default: BitRule) -> Self {
+        Criteria{default, rules: Vec::new()}
+    }
 
-        let width = lines[0].len();
+    pub fn with_rule(mut self, position: usize, rule: BitRule) -> Self {
+        if self.rules.len() <= position {
+            self.rules.resize(position + 1, None);
+        }
+        self.rules[position] = Some(rule);
+        self
+    }
 
-        for i in 0 .. width {
-            o2_generator_rating *= 2;
-            zero_count = count_zeroes_at(&lines[slice_pos .. slice_pos + slice_size], i);
-            let one_count = slice_size - zero_count;
-
-            if one_count >= zero_count {
-                o2_generator_rating += 1;
-                slice_pos += zero_count;
-                slice_size = one_count;
-            } else {
-                slice_size = zero_count;
-            }
+    // space separated rules for consecutive positions, the last one applies
+    // to all remaining positions: "M1" most common with ties to 1, "L0" least
+    // common with ties to 0, "0"/"1" fixed bit, "*" skip
+    pub fn parse(query: &str) -> Result<Self, String> {
+        let mut rules : Vec<BitRule> = Vec::new();
+        for token in query.split_whitespace() {
+            let rule = match token {
+                "M0" => BitRule::MostCommon{tie: Bit::Zero},
+                "M1" => BitRule::MostCommon{tie: Bit::One},
+                "L0" => BitRule::LeastCommon{tie: Bit::Zero},
+                "L1" => BitRule::LeastCommon{tie: Bit::One},
+                "0" => BitRule::Fixed(Bit::Zero),
+                "1" => BitRule::Fixed(Bit::One),
+                "*" => BitRule::Skip,
+                _ => return Err(format!("unknown rule '{}'", token)),
+            };
+            rules.push(rule);
         }
 
-        o2_generator_rating
+        let default = match rules.pop() {
+            Some(rule) => rule,
+            None => return Err("empty query".to_string()),
+        };
+
+        let mut criteria = Criteria::new(default);
+        for (position, rule) in rules.into_iter().enumerate() {
+            criteria = criteria.with_rule(position, rule);
+        }
+        Ok(criteria)
+    }
+
+    pub fn rule_at(&self, position: usize) -> BitRule {
+        self.rules.get(position).copied().flatten().unwrap_or(self.default)
+    }
 }
 
-fn get_co2_scrubber_rating(lines: &[&str]) -> u32 {
-        let mut co2_scrubber_rating : u32 = 0;
+#[derive(Debug, PartialEq)]
+struct Rating<'a> {
+    line: &'a str,
+    index: usize,
+    value: u32,
+}
 
-        let mut slice_size = lines.len();
-        let mut slice_pos : usize = 0;
-        let mut zero_count : usize = 0;
+struct TrieNode {
+    count: usize,
+    first_index: usize,
+    children: [Option<usize>; 2],
+}
 
+struct BitTrie<'a> {
+    lines: Vec<&'a str>,
+    nodes: Vec<TrieNode>,
+    width: usize,
+}
+
+impl<'a> BitTrie<'a> {
+    pub fn new(lines: &[&'a str]) -> Self {
         let width = lines[0].len();
+        let mut trie = BitTrie{lines: lines.to_vec(), nodes: Vec::new(), width};
+        trie.nodes.push(TrieNode{count: 0, first_index: 0, children: [None, None]});
+
+        for (index, line) in lines.iter().enumerate() {
+            assert_eq!(line.len(), width);
+            let mut node = 0;
+            trie.nodes[node].count += 1;
+            for c in line.chars() {
+                let bit = match c {
+                    '0' => 0,
+                    '1' => 1,
+                    _ => panic!("unexpected character '{}' in line '{}'", c, line),
+                };
+                node = match trie.nodes[node].children[bit] {
+                    Some(child) => child,
+                    None => {
+                        trie.nodes.push(TrieNode{count: 0, first_index: index, children: [None, None]});
+                        let child = trie.nodes.len() - 1;
+                        trie.nodes[node].children[bit] = Some(child);
+                        child
+                    }
+                };
+                trie.nodes[node].count += 1;
+            }
+        }
 
-        for i in 0 .. width {
-            co2_scrubber_rating *= 2;
-            zero_count = count_zeroes_at(&lines[slice_pos .. slice_pos + slice_size], i);
-            let one_count = slice_size - zero_count;
-
-            if (one_count > 0 && one_count < zero_count) || (zero_count == 0) {
-                co2_scrubber_rating += 1;
-                slice_pos += zero_count;
-                slice_size = one_count;
-            } else {
-                slice_size = zero_count;
+        trie
+    }
+
+    fn count(&self, child: Option<usize>) -> usize {
+        match child {
+            Some(node) => self.nodes[node].count,
+            None => 0,
+        }
+    }
+
+    pub fn filter(&self, criteria: &Criteria) -> Option<Rating<'a>> {
+        let mut survivors : Vec<usize> = vec![0];
+
+        // a single survivor still has to pass the fixed rules further on, the
+        // common value rules simply follow its bits
+        for position in 0 .. self.width {
+            if survivors.is_empty() {
+                return None;
             }
+            let total : usize = survivors.iter().map(|&n| self.nodes[n].count).sum();
+
+            let zero_count : usize = survivors.iter().map(|&n| self.count(self.nodes[n].children[0])).sum();
+            let one_count = total - zero_count;
+
+            let bits : Vec<usize> = match criteria.rule_at(position).select(zero_count, one_count) {
+                Some(bit) => vec![bit as usize],
+                None => vec![0, 1],
+            };
+
+            survivors = survivors.iter()
+                .flat_map(|&n| bits.iter().filter_map(move |&bit| self.nodes[n].children[bit]))
+                .collect();
         }
 
-        co2_scrubber_rating
+        let index = survivors.iter().map(|&n| self.nodes[n].first_index).min()?;
+        let line = self.lines[index];
+        Some(Rating{line, index, value: u32::from_str_radix(line, 2).unwrap()})
+    }
+}
+
+fn o2_generator_criteria() -> Criteria {
+    Criteria::new(BitRule::MostCommon{tie: Bit::One})
+}
+
+fn co2_scrubber_criteria() -> Criteria {
+    Criteria::new(BitRule::LeastCommon{tie: Bit::Zero})
+}
+
+fn find_o2_generator_rating<'a>(trie: &BitTrie<'a>) -> Rating<'a> {
+    trie.filter(&o2_generator_criteria()).unwrap()
+}
+
+fn find_co2_scrubber_rating<'a>(trie: &BitTrie<'a>) -> Rating<'a> {
+    trie.filter(&co2_scrubber_criteria()).unwrap()
 }
 
 fn main()  {
         let contents = fs::read_to_string("input.txt").expect("Input file read failed");
-        let mut lines : Vec<&str> = Vec::new();
-        lines.extend(contents.split("\n").filter(|l| ! l.is_empty()));
-        lines.sort();
+        let lines : Vec<&str> = contents.split('\n').filter(|l| ! l.is_empty()).collect();
+
+        let trie = BitTrie::new(&lines);
 
-        println!("{}", get_o2_generator_rating(&lines[..])*get_co2_scrubber_rating(&lines[..]));
+        let args : Vec<String> = env::args().collect();
+        if args.len() == 2 {
+            let criteria = Criteria::parse(&args[1]).expect("invalid query");
+            match trie.filter(&criteria) {
+                Some(rating) => println!("{} (line {}) = {}", rating.line, rating.index + 1, rating.value),
+                None => println!("no line matches"),
+            }
+            return;
+        }
+
+        let o2_generator_rating = find_o2_generator_rating(&trie);
+        let co2_scrubber_rating = find_co2_scrubber_rating(&trie);
+
+        println!("{}", o2_generator_rating.value*co2_scrubber_rating.value);
 }
 
 #[cfg(test)]
+#[allow(clippy::vec_init_then_push)]
 mod tests {
     use super::*;
 
-#[test]
-    fn test_count_zeroes_at() {
-        let mut data : Vec<&str> = Vec::new();
-        data.push("00001");
-        data.push("00011");
-        data.push("00101");
-        data.push("00111");
-        data.push("01001");
-        data.push("01011");
-        data.push("01101");
-        data.push("01111");
+    fn get_o2_generator_rating(lines: &[&str]) -> u32 {
+        find_o2_generator_rating(&BitTrie::new(lines)).value
+    }
 
-        assert_eq!(count_zeroes_at(&data[0 .. 7], 0), 7);
-        assert_eq!(count_zeroes_at(&data[0 .. 7], 1), 4);
-        assert_eq!(count_zeroes_at(&data[0 .. 7], 4), 0);
+    fn get_co2_scrubber_rating(lines: &[&str]) -> u32 {
+        find_co2_scrubber_rating(&BitTrie::new(lines)).value
     }
 
 #[test]
@@ -193,4 +319,91 @@ mod tests {
         assert_eq!(get_co2_scrubber_rating(&data[..]), 10)
     }
 
+#[test]
+    fn test_filter_returns_line_and_index() {
+        let data : Vec<&str> = vec!["00100", "11110", "10110", "10111", "10101", "01111", "00111", "11100", "10000", "11001", "00010", "01010"];
+        let trie = BitTrie::new(&data);
+
+        assert_eq!(find_o2_generator_rating(&trie), Rating{line: "10111", index: 3, value: 23});
+        assert_eq!(find_co2_scrubber_rating(&trie), Rating{line: "01010", index: 11, value: 10});
+    }
+
+#[test]
+    fn test_filter_tie_breaking() {
+        let data : Vec<&str> = vec!["01", "10", "11", "00"];
+        let trie = BitTrie::new(&data);
+
+        assert_eq!(trie.filter(&Criteria::new(BitRule::MostCommon{tie: Bit::One})).unwrap().line, "11");
+        assert_eq!(trie.filter(&Criteria::new(BitRule::MostCommon{tie: Bit::Zero})).unwrap().line, "00");
+        assert_eq!(trie.filter(&Criteria::new(BitRule::LeastCommon{tie: Bit::Zero})).unwrap().line, "00");
+        assert_eq!(trie.filter(&Criteria::new(BitRule::LeastCommon{tie: Bit::One})).unwrap().index, 2);
+    }
+
+#[test]
+    fn test_filter_duplicate_lines() {
+        let data : Vec<&str> = vec!["10", "01", "01"];
+        let trie = BitTrie::new(&data);
+
+        assert_eq!(find_o2_generator_rating(&trie), Rating{line: "01", index: 1, value: 1});
+        assert_eq!(find_co2_scrubber_rating(&trie).index, 0);
+    }
+
+#[test]
+    fn test_filter_fixed_and_skip_rules() {
+        let data : Vec<&str> = vec!["000", "011", "101", "110", "111"];
+        let trie = BitTrie::new(&data);
+
+        let criteria = Criteria::new(BitRule::MostCommon{tie: Bit::One}).with_rule(0, BitRule::Fixed(Bit::Zero));
+        assert_eq!(trie.filter(&criteria).unwrap().line, "011");
+
+        let criteria = Criteria::new(BitRule::LeastCommon{tie: Bit::One}).with_rule(0, BitRule::Skip);
+        assert_eq!(trie.filter(&criteria).unwrap().line, "101");
+
+        let criteria = Criteria::new(BitRule::Fixed(Bit::One)).with_rule(1, BitRule::Skip);
+        assert_eq!(trie.filter(&criteria).unwrap().line, "101");
+
+        let criteria = Criteria::new(BitRule::Skip);
+        assert_eq!(trie.filter(&criteria).unwrap().index, 0);
+
+        let data : Vec<&str> = vec!["10", "11"];
+        let trie = BitTrie::new(&data);
+        assert_eq!(trie.filter(&Criteria::new(BitRule::Fixed(Bit::Zero))), None);
+    }
+
+#[test]
+    fn test_filter_fixed_rules_after_single_survivor() {
+        let data : Vec<&str> = vec!["000", "011", "101"];
+        let trie = BitTrie::new(&data);
+        assert_eq!(trie.filter(&Criteria::parse("0 0 1").unwrap()), None);
+        assert_eq!(trie.filter(&Criteria::parse("0 0 0").unwrap()).unwrap().line, "000");
+        assert_eq!(trie.filter(&Criteria::parse("1 M0").unwrap()).unwrap().line, "101");
+        assert_eq!(trie.filter(&Criteria::parse("1 L1 1").unwrap()).unwrap().line, "101");
+
+        let data : Vec<&str> = vec!["01"];
+        let trie = BitTrie::new(&data);
+        assert_eq!(trie.filter(&Criteria::parse("1").unwrap()), None);
+        assert_eq!(trie.filter(&Criteria::parse("0 1").unwrap()).unwrap().line, "01");
+    }
+
+#[test]
+    fn test_parse_criteria() {
+        let criteria = Criteria::parse("1 * L0").unwrap();
+        assert_eq!(criteria.rule_at(0), BitRule::Fixed(Bit::One));
+        assert_eq!(criteria.rule_at(1), BitRule::Skip);
+        assert_eq!(criteria.rule_at(2), BitRule::LeastCommon{tie: Bit::Zero});
+        assert_eq!(criteria.rule_at(7), BitRule::LeastCommon{tie: Bit::Zero});
+
+        assert!(Criteria::parse("").is_err());
+        assert!(Criteria::parse("M2").is_err());
+    }
+
+#[test]
+    fn test_parsed_criteria_match_builtin_ratings() {
+        let data : Vec<&str> = vec!["00100", "11110", "10110", "10111", "10101", "01111", "00111", "11100", "10000", "11001", "00010", "01010"];
+        let trie = BitTrie::new(&data);
+
+        assert_eq!(trie.filter(&Criteria::parse("M1").unwrap()), Some(find_o2_generator_rating(&trie)));
+        assert_eq!(trie.filter(&Criteria::parse("L0").unwrap()), Some(find_co2_scrubber_rating(&trie)));
+    }
+
 }
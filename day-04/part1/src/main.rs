@@ -1,88 +1,332 @@
+use std::env;
 use std::fs;
 use std::collections::HashSet;
+use ansi_term::Style;
 
-const BOARD_SIZE : usize = 5;
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum WinPattern {
+    Rows,
+    Columns,
+    Diagonals,
+    FourCorners,
+    FullCard,
+}
+
+const STANDARD_PATTERNS : [WinPattern; 2] = [WinPattern::Rows, WinPattern::Columns];
+
+fn parse_patterns(text: &str) -> Result<Vec<WinPattern>, String> {
+    let mut result : Vec<WinPattern> = Vec::new();
+    for name in text.split(',') {
+        let pattern = match name {
+            "rows" => WinPattern::Rows,
+            "columns" => WinPattern::Columns,
+            "diagonals" => WinPattern::Diagonals,
+            "corners" => WinPattern::FourCorners,
+            "full" => WinPattern::FullCard,
+            _ => return Err(format!("unknown win pattern '{}'", name)),
+        };
+        result.push(pattern);
+    }
+    Ok(result)
+}
+
+fn parse_number(number: &str) -> Result<u32, String> {
+    number.parse().map_err(|_| format!("'{}' is not a number", number))
+}
 
-fn parse_input(line: &str) -> Vec<u32> {
+fn parse_input(line: &str) -> Result<Vec<u32>, String> {
     let mut result : Vec<u32> = Vec::new();
-    let numbers = line.split(",");
+    let numbers = line.split(',');
     for number in numbers {
-        result.push(number.parse().unwrap());
+        result.push(parse_number(number)?);
     }
-    result
+    Ok(result)
 }
 
-fn parse_board(lines: &[&str]) -> Vec<Vec<u32>> {
+fn parse_board(lines: &[&str]) -> Result<Vec<Vec<u32>>, String> {
     let mut result : Vec<Vec<u32>> = Vec::new();
 
-    for line in lines {
+    for (i, line) in lines.iter().enumerate() {
         let mut line_numbers : Vec<u32> = Vec::new();
         for number in line.split_whitespace() {
-            line_numbers.push(number.parse().unwrap())
+            line_numbers.push(parse_number(number).map_err(|e| format!("row {}: {}", i + 1, e))?)
         }
         result.push(line_numbers);
     }
 
-    result
+    Ok(result)
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Board {
+    grid: Vec<Vec<u32>>,
+    marked: Vec<Vec<bool>>,
+}
+
+impl Board {
+    pub fn new(grid: Vec<Vec<u32>>) -> Result<Self, String> {
+        if grid.is_empty() {
+            return Err("board has no rows".to_string());
+        }
+
+        let cols = grid[0].len();
+        let mut seen : HashSet<u32> = HashSet::new();
+        for (i, row) in grid.iter().enumerate() {
+            if row.len() != cols {
+                return Err(format!("row {} has {} numbers, expected {}", i + 1, row.len(), cols));
+            }
+            for number in row {
+                if ! seen.insert(*number) {
+                    return Err(format!("number {} appears more than once", number));
+                }
+            }
+        }
+
+        let marked = vec![vec![false; cols]; grid.len()];
+        Ok(Board{grid, marked})
+    }
+
+    pub fn rows(&self) -> usize {
+        self.grid.len()
+    }
+
+    pub fn cols(&self) -> usize {
+        self.grid[0].len()
+    }
+
+    pub fn mark(&mut self, number: u32) -> bool {
+        for (i, row) in self.grid.iter().enumerate() {
+            if let Some(j) = row.iter().position(|&n| n == number) {
+                self.marked[i][j] = true;
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn unmarked_sum(&self) -> u32 {
+        let mut result : u32 = 0;
+        for (row, marked_row) in self.grid.iter().zip(&self.marked) {
+            for (number, marked) in row.iter().zip(marked_row) {
+                if ! marked {
+                    result += number;
+                }
+            }
+        }
+        result
+    }
+
+    // marked numbers are printed bold
+    pub fn render(&self) -> String {
+        let width = self.grid.iter().flatten().map(|n| n.to_string().len()).max().unwrap();
+        let mut result = String::new();
+        for (row, marked_row) in self.grid.iter().zip(&self.marked) {
+            let cells : Vec<String> = row.iter().zip(marked_row).map(|(number, &marked)| {
+                let val = format!("{:>width$}", number, width = width);
+                if marked {
+                    Style::new().bold().paint(val).to_string()
+                } else {
+                    val
+                }
+            }).collect();
+            result.push_str(&cells.join(" "));
+            result.push('\n');
+        }
+        result
+    }
 }
 
-fn generate_board_sets(board: Vec<Vec<u32>>) -> Vec<HashSet<u32>> {
-    let board_size = board.len();
-    let mut result : Vec<HashSet<u32>> = vec![HashSet::new(); 2*board_size+2];
+fn parse_game(text: &str) -> Result<(Vec<u32>, Vec<Board>), String> {
+    let mut blocks = text.split("\n\n").map(|b| b.trim_matches('\n')).filter(|b| ! b.is_empty());
+    let input_numbers = parse_input(blocks.next().ok_or("missing input numbers")?).map_err(|e| format!("input numbers: {}", e))?;
 
-    for (i, row) in board.iter().enumerate() {
-        assert_eq!(row.len(), board_size);
-        for (j, number) in row.iter().enumerate() {
-            assert_eq!(result[i].contains(&number), false);
-            result[i].insert(number.clone());
-            result[board_size + j].insert(number.clone());
-            if i == j {
-                assert_eq!(result[board_size*2].contains(&number), false);
-                result[board_size*2].insert(number.clone());
+    let mut boards : Vec<Board> = Vec::new();
+    for (i, block) in blocks.enumerate() {
+        let lines : Vec<&str> = block.split('\n').collect();
+        let board = parse_board(&lines).and_then(Board::new).map_err(|e| format!("board {}: {}", i + 1, e))?;
+        if let Some(first) = boards.first() {
+            if board.rows() != first.rows() {
+                return Err(format!("board {}: has {} rows, expected {}", i + 1, board.rows(), first.rows()));
             }
-            if i + j == board_size-1 {
-                assert_eq!(result[board_size*2+1].contains(&number), false);
-                result[board_size*2+1].insert(number.clone());
+            if board.cols() != first.cols() {
+                return Err(format!("board {}: has {} columns, expected {}", i + 1, board.cols(), first.cols()));
             }
         }
+        boards.push(board);
     }
 
+    Ok((input_numbers, boards))
+}
+
+fn boards_after_turn(input_numbers: &[u32], boards: &[Board], turn: usize) -> Vec<Board> {
+    let mut result = boards.to_vec();
+    for board in &mut result {
+        for &input in &input_numbers[..turn.min(input_numbers.len())] {
+            board.mark(input);
+        }
+    }
     result
 }
 
-fn sum_board_remains(board: &Vec<HashSet<u32>>) -> u32 {
-    let mut result : u32 = 0;
-    for set in &board[0..BOARD_SIZE] {
-        for number in set {
-            result += number;
+fn generate_board_lines(board: &[Vec<u32>], patterns: &[WinPattern]) -> Vec<Vec<u32>> {
+    let rows = board.len();
+    let cols = board[0].len();
+
+    let mut all_numbers : HashSet<u32> = HashSet::new();
+    for row in board {
+        assert_eq!(row.len(), cols);
+        for number in row {
+            assert!(all_numbers.insert(*number), "duplicate number {} on board", number);
+        }
+    }
+
+    let mut result : Vec<Vec<u32>> = Vec::new();
+
+    for pattern in patterns {
+        match pattern {
+            WinPattern::Rows => {
+                for row in board {
+                    result.push(row.clone());
+                }
+            },
+            WinPattern::Columns => {
+                for j in 0..cols {
+                    result.push(board.iter().map(|row| row[j]).collect());
+                }
+            },
+            WinPattern::Diagonals => {
+                assert_eq!(rows, cols, "diagonals need a square board");
+                result.push((0..rows).map(|i| board[i][i]).collect());
+                result.push((0..rows).map(|i| board[rows-1-i][i]).collect());
+            },
+            WinPattern::FourCorners => {
+                result.push(vec![board[0][0], board[0][cols-1], board[rows-1][0], board[rows-1][cols-1]]);
+            },
+            WinPattern::FullCard => {
+                result.push(board.iter().flatten().cloned().collect());
+            },
+        }
+    }
+
+    result
+}
+
+fn generate_board_sets(board: Vec<Vec<u32>>, patterns: &[WinPattern]) -> Vec<HashSet<u32>> {
+    generate_board_lines(&board, patterns).into_iter().map(|line| line.into_iter().collect()).collect()
+}
+
+struct BoardState {
+    board: Board,
+    lines: Vec<Vec<u32>>,
+    winning_sets: Vec<HashSet<u32>>,
+}
+
+impl BoardState {
+    pub fn new(board: Board, patterns: &[WinPattern]) -> Self {
+        let lines = generate_board_lines(&board.grid, patterns);
+        let winning_sets = generate_board_sets(board.grid.clone(), patterns);
+        BoardState{board, lines, winning_sets}
+    }
+
+    // returns index of the first line completed by this number
+    pub fn mark(&mut self, number: u32) -> Option<usize> {
+        self.board.mark(number);
+        let mut won = None;
+        for (i, board_set) in self.winning_sets.iter_mut().enumerate() {
+            if board_set.remove(&number) && board_set.is_empty() && won.is_none() {
+                won = Some(i);
+            }
+        }
+        won
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct WinRecord {
+    board: usize,
+    turn: usize,
+    line: Vec<u32>,
+    number: u32,
+    score: u32,
+}
+
+// plays all the input numbers, returns records of the boards that won in
+// the order of winning
+fn play_game(input_numbers: &[u32], mut board_state: Vec<BoardState>) -> Vec<WinRecord> {
+    let mut result : Vec<WinRecord> = Vec::new();
+    let mut board_already_won : Vec<bool> = vec![false; board_state.len()];
+
+    for (turn, &input) in input_numbers.iter().enumerate() {
+        for (i, board) in board_state.iter_mut().enumerate() {
+            if board_already_won[i] {
+                continue;
+            }
+            if let Some(line) = board.mark(input) {
+                board_already_won[i] = true;
+                result.push(WinRecord{
+                    board: i,
+                    turn: turn + 1,
+                    line: board.lines[line].clone(),
+                    number: input,
+                    score: sum_board_remains(board)*input,
+                });
+            }
         }
     }
+
     result
 }
 
+fn sum_board_remains(board: &BoardState) -> u32 {
+    board.board.unmarked_sum()
+}
+
+fn get_option<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    let prefix = format!("--{}=", name);
+    args.iter().find_map(|a| a.strip_prefix(&prefix))
+}
+
 fn main()  {
     let contents = fs::read_to_string("input.txt").expect("Input file read failed");
-    let mut lines : Vec<&str> = Vec::new();
-    lines.extend(contents.split("\n").filter(|l| ! l.is_empty()));
 
-    let input_numbers = parse_input(lines[0]);
+    let args : Vec<String> = env::args().skip(1).collect();
+    let print_transcript = args.iter().any(|a| a == "--transcript");
+    let patterns = match args.iter().find(|a| ! a.starts_with("--")) {
+        Some(text) => parse_patterns(text).expect("invalid win patterns"),
+        None => STANDARD_PATTERNS.to_vec(),
+    };
+
+    let (input_numbers, boards) = match parse_game(&contents) {
+        Ok(game) => game,
+        Err(message) => panic!("invalid input: {}", message),
+    };
+    let board_count = boards.len();
 
-    let mut board_state : Vec<Vec<HashSet<u32>>> = Vec::new();
-    for i in (1..lines.len()).step_by(BOARD_SIZE) {
-        board_state.push(generate_board_sets(parse_board(&lines[i..i+BOARD_SIZE])))
+    if let Some(turn) = get_option(&args, "render") {
+        let turn : usize = turn.parse().expect("invalid turn");
+        for (i, board) in boards_after_turn(&input_numbers, &boards, turn).iter().enumerate() {
+            println!("board {}:", i + 1);
+            println!("{}", board.render());
+        }
+        return;
     }
 
-    'input_loop: for input in input_numbers {
-        for board in &mut board_state {
-            for board_set in &mut *board {
-                board_set.remove(&input);
-                if board_set.is_empty() {
-                    println!("{}", sum_board_remains(board)*input);
-                    break 'input_loop;
-                }
+    let board_state : Vec<BoardState> = boards.into_iter().map(|b| BoardState::new(b, &patterns)).collect();
+    let transcript = play_game(&input_numbers, board_state);
+
+    if print_transcript {
+        for record in &transcript {
+            println!("board {} won on turn {} drawing {} with line {:?}, score {}",
+                record.board + 1, record.turn, record.number, record.line, record.score);
+        }
+        for board in 0..board_count {
+            if ! transcript.iter().any(|r| r.board == board) {
+                println!("board {} never won", board + 1);
             }
         }
     }
+
+    println!("{}", transcript.first().expect("no board won").score);
 }
 
 #[cfg(test)]
@@ -91,7 +335,7 @@ mod tests {
 
 #[test]
     fn test_parse_input() {
-        assert_eq!(parse_input("85,84"), vec![85,84]);
+        assert_eq!(parse_input("85,84"), Ok(vec![85,84]));
     }
 
 #[test]
@@ -104,7 +348,7 @@ mod tests {
             "49  1 95 71 17",
         ];
 
-        let output = parse_board(&input[..]);
+        let output = parse_board(&input[..]).unwrap();
 
         let expected_output : Vec<Vec<u32>> = vec![
             vec![78, 13,  8, 62, 67],
@@ -128,7 +372,7 @@ mod tests {
             vec![49,  1, 95, 71, 17],
         ];
 
-        let output = generate_board_sets(input);
+        let output = generate_board_sets(input, &[WinPattern::Rows, WinPattern::Columns, WinPattern::Diagonals]);
 
         let expected_output : Vec<HashSet<u32>> = vec![
             [78, 13,  8, 62, 67].iter().cloned().collect(),
@@ -149,4 +393,113 @@ mod tests {
 
         assert_eq!(output, expected_output);
     }
+
+#[test]
+    fn test_parse_patterns() {
+        assert_eq!(parse_patterns("rows,columns,diagonals"), Ok(vec![WinPattern::Rows, WinPattern::Columns, WinPattern::Diagonals]));
+        assert_eq!(parse_patterns("corners,full"), Ok(vec![WinPattern::FourCorners, WinPattern::FullCard]));
+        assert!(parse_patterns("rows,stars").is_err());
+    }
+
+#[test]
+    fn test_parse_game_infers_board_size() {
+        let (input_numbers, boards) = parse_game("1,2,3\n\n1 2 3\n4 5 6\n\n 9  8 7\n 6  5 4\n").unwrap();
+        assert_eq!(input_numbers, vec![1, 2, 3]);
+        assert_eq!(boards.iter().map(|b| b.grid.clone()).collect::<Vec<Vec<Vec<u32>>>>(), vec![
+            vec![vec![1, 2, 3], vec![4, 5, 6]],
+            vec![vec![9, 8, 7], vec![6, 5, 4]],
+        ]);
+    }
+
+#[test]
+    fn test_generate_board_sets_corners_and_full_card() {
+        let input : Vec<Vec<u32>> = vec![
+            vec![1, 2, 3],
+            vec![4, 5, 6],
+        ];
+
+        let output = generate_board_sets(input, &[WinPattern::FourCorners, WinPattern::FullCard]);
+
+        let expected_output : Vec<HashSet<u32>> = vec![
+            [1, 3, 4, 6].iter().cloned().collect(),
+            [1, 2, 3, 4, 5, 6].iter().cloned().collect(),
+        ];
+
+        assert_eq!(output, expected_output);
+    }
+
+#[test]
+    fn test_board_state_standard_patterns_ignore_diagonals() {
+        let board : Vec<Vec<u32>> = vec![
+            vec![1, 2],
+            vec![3, 4],
+        ];
+
+        let mut state = BoardState::new(Board::new(board.clone()).unwrap(), &STANDARD_PATTERNS);
+        assert_eq!(state.mark(1), None);
+        assert_eq!(state.mark(4), None);
+        assert_eq!(state.mark(2), Some(0));
+        assert_eq!(sum_board_remains(&state), 3);
+
+        let mut state = BoardState::new(Board::new(board).unwrap(), &[WinPattern::Rows, WinPattern::Columns, WinPattern::Diagonals]);
+        assert_eq!(state.mark(1), None);
+        assert_eq!(state.mark(4), Some(4));
+        assert_eq!(sum_board_remains(&state), 5);
+    }
+
+#[test]
+    fn test_play_game_upstream_example() {
+        let text = "7,4,9,5,11,17,23,2,0,14,21,24,10,16,13,6,15,25,12,22,18,20,8,19,3,26,1\n\n\
+            22 13 17 11  0\n 8  2 23  4 24\n21  9 14 16  7\n 6 10  3 18  5\n 1 12 20 15 19\n\n\
+             3 15  0  2 22\n 9 18 13 17  5\n19  8  7 25 23\n20 11 10 24  4\n14 21 16 12  6\n\n\
+            14 21 17 24  4\n10 16 15  9 19\n18  8 23 26 20\n22 11 13  6  5\n 2  0 12  3  7\n";
+
+        let (input_numbers, boards) = parse_game(text).unwrap();
+        let board_state : Vec<BoardState> = boards.into_iter().map(|b| BoardState::new(b, &STANDARD_PATTERNS)).collect();
+        let transcript = play_game(&input_numbers, board_state);
+
+        assert_eq!(transcript, vec![
+            WinRecord{board: 2, turn: 12, line: vec![14, 21, 17, 24, 4], number: 24, score: 4512},
+            WinRecord{board: 0, turn: 14, line: vec![21, 9, 14, 16, 7], number: 16, score: 2192},
+            WinRecord{board: 1, turn: 15, line: vec![0, 13, 7, 10, 16], number: 13, score: 1924},
+        ]);
+    }
+
+#[test]
+    fn test_play_game_board_that_never_wins() {
+        let board_state = vec![
+            BoardState::new(Board::new(vec![vec![1, 2], vec![3, 4]]).unwrap(), &STANDARD_PATTERNS),
+            BoardState::new(Board::new(vec![vec![5, 6], vec![7, 8]]).unwrap(), &STANDARD_PATTERNS),
+        ];
+
+        let transcript = play_game(&[5, 1, 3, 6], board_state);
+
+        assert_eq!(transcript, vec![
+            WinRecord{board: 0, turn: 3, line: vec![1, 3], number: 3, score: 6*3},
+            WinRecord{board: 1, turn: 4, line: vec![5, 6], number: 6, score: 15*6},
+        ]);
+        let transcript = play_game(&[5, 1, 3], vec![BoardState::new(Board::new(vec![vec![5, 6], vec![7, 8]]).unwrap(), &STANDARD_PATTERNS)]);
+        assert!(transcript.is_empty());
+    }
+
+#[test]
+    fn test_parse_game_malformed_boards() {
+        assert_eq!(parse_game("1,2\n\n1 2\n3 4 5\n").unwrap_err(), "board 1: row 2 has 3 numbers, expected 2");
+        assert_eq!(parse_game("1,2\n\n1 2\n3 1\n").unwrap_err(), "board 1: number 1 appears more than once");
+        assert_eq!(parse_game("1,2\n\n1 2\n3 4\n\n5 6\n").unwrap_err(), "board 2: has 1 rows, expected 2");
+        assert_eq!(parse_game("1,2\n\n1 2\n3 4\n\n5 6 7\n8 9 10\n").unwrap_err(), "board 2: has 3 columns, expected 2");
+        assert!(parse_game("").is_err());
+        assert_eq!(parse_game("1,x\n\n1 2\n3 4\n").unwrap_err(), "input numbers: 'x' is not a number");
+        assert_eq!(parse_game("1,2\n\n1 2\n3 4\n\n5 6\n7 8\n9 x\n").unwrap_err(), "board 2: row 3: 'x' is not a number");
+    }
+
+#[test]
+    fn test_board_render() {
+        let board = Board::new(vec![vec![1, 12], vec![7, 3]]).unwrap();
+        let boards = boards_after_turn(&[12, 3, 1], &[board], 2);
+
+        let bold = |val: &str| Style::new().bold().paint(val).to_string();
+        assert_eq!(boards[0].render(), format!(" 1 {}\n 7 {}\n", bold("12"), bold(" 3")));
+        assert_eq!(boards[0].unmarked_sum(), 8);
+    }
 }
@@ -1,4 +1,5 @@
 use std::fs;
+#[cfg(test)]
 use std::collections::HashMap;
 
 fn parse_input_lines(lines: &Vec<&str>) -> Vec<Vec<u32>> {
@@ -15,6 +16,7 @@ fn parse_input_lines(lines: &Vec<&str>) -> Vec<Vec<u32>> {
     result
 }
 
+#[cfg(test)]
 fn generate_line_points(lines: &Vec<Vec<u32>>) -> Vec<(u32, u32)> {
     let mut line_points: Vec<(u32, u32)> = Vec::new();
 
@@ -22,9 +24,7 @@ fn generate_line_points(lines: &Vec<Vec<u32>>) -> Vec<(u32, u32)> {
         if let [mut ax, mut ay, mut bx, mut by] = line[..] {
             if ax == bx {
                 if ay > by {
-                    let tmp = ay;
-                    ay = by;
-                    by = tmp;
+                    std::mem::swap(&mut ay, &mut by);
                 }
                 assert!(by > ay);
                 for i in ay..=by {
@@ -32,15 +32,12 @@ fn generate_line_points(lines: &Vec<Vec<u32>>) -> Vec<(u32, u32)> {
                 }
             } else if ay == by {
                 if ax > bx {
-                    let tmp = ax;
-                    ax = bx;
-                    bx = tmp;
+                    std::mem::swap(&mut ax, &mut bx);
                 }
                 assert!(bx >= ax);
                 for i in ax..=bx {
                     line_points.push((i, ay));
                 }
-            } else {
             }
 
         }
@@ -49,6 +46,7 @@ fn generate_line_points(lines: &Vec<Vec<u32>>) -> Vec<(u32, u32)> {
     line_points
 }
 
+#[cfg(test)]
 fn count_line_points(line_points: &Vec<(u32, u32)>) -> HashMap<(u32, u32), u32> {
     let mut result: HashMap<(u32, u32), u32> = HashMap::new();
 
@@ -59,10 +57,11 @@ fn count_line_points(line_points: &Vec<(u32, u32)>) -> HashMap<(u32, u32), u32>
     result
 }
 
+#[cfg(test)]
 fn count_overlaps(points: &HashMap<(u32, u32), u32>) -> usize {
     let mut result: usize = 0; 
 
-    for (_, coverage) in points {
+    for coverage in points.values() {
         if *coverage >= 2 {
             result += 1;
         }
@@ -71,15 +70,115 @@ fn count_overlaps(points: &HashMap<(u32, u32), u32>) -> usize {
     result
 }
 
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    x0: i64,
+    y0: i64,
+    x1: i64,
+    y1: i64,
+    slope: i64,
+}
+
+impl Segment {
+    pub fn new(line: &[u32]) -> Self {
+        let (mut ax, mut ay, mut bx, mut by) = (line[0] as i64, line[1] as i64, line[2] as i64, line[3] as i64);
+        if ax > bx || (ax == bx && ay > by) {
+            std::mem::swap(&mut ax, &mut bx);
+            std::mem::swap(&mut ay, &mut by);
+        }
+        let dx = bx - ax;
+        let dy = by - ay;
+        assert!(dx == 0 || dy == 0 || dx == dy.abs(), "unsupported slope in line {:?}", line);
+        Segment{x0: ax, y0: ay, x1: bx, y1: by, slope: if dx == 0 { 0 } else { dy / dx }}
+    }
+
+    pub fn is_vertical(&self) -> bool {
+        self.x0 == self.x1
+    }
+
+    pub fn is_diagonal(&self) -> bool {
+        self.slope != 0
+    }
+}
+
+// counts points covered at least twice within column x of the active segments
+fn count_column_overlaps(active: &[Segment], x: i64) -> i64 {
+    let mut events : Vec<(i64, i64)> = Vec::new();
+    for segment in active {
+        if x < segment.x0 || x > segment.x1 {
+            continue;
+        }
+        if segment.is_vertical() {
+            events.push((segment.y0, 1));
+            events.push((segment.y1 + 1, -1));
+        } else {
+            let y = segment.y0 + segment.slope*(x - segment.x0);
+            events.push((y, 1));
+            events.push((y + 1, -1));
+        }
+    }
+    events.sort();
+
+    let mut result : i64 = 0;
+    let mut coverage : i64 = 0;
+    let mut previous_y : i64 = 0;
+    for (y, delta) in events {
+        if coverage >= 2 {
+            result += y - previous_y;
+        }
+        coverage += delta;
+        previous_y = y;
+    }
+    result
+}
+
+// sweeps the segments from left to right; columns between two events look the
+// same unless a diagonal is active, so those are only counted once
+fn count_overlaps_sweep(lines: &[Vec<u32>]) -> usize {
+    let mut segments : Vec<Segment> = lines.iter().map(|l| Segment::new(l)).collect();
+    segments.sort_by_key(|s| s.x0);
+
+    let mut events : Vec<i64> = segments.iter().flat_map(|s| [s.x0, s.x1 + 1]).collect();
+    events.sort();
+    events.dedup();
+
+    let mut active : Vec<Segment> = Vec::new();
+    let mut next_segment : usize = 0;
+    let mut result : i64 = 0;
+
+    for (i, &x) in events.iter().enumerate() {
+        active.retain(|s| s.x1 >= x);
+        while next_segment < segments.len() && segments[next_segment].x0 == x {
+            active.push(segments[next_segment]);
+            next_segment += 1;
+        }
+
+        result += count_column_overlaps(&active, x);
+
+        let run_end = if i + 1 < events.len() { events[i + 1] } else { x + 1 };
+        if run_end > x + 1 {
+            if active.iter().any(|s| s.is_diagonal()) {
+                for column in x + 1 .. run_end {
+                    result += count_column_overlaps(&active, column);
+                }
+            } else {
+                result += count_column_overlaps(&active, x + 1)*(run_end - x - 1);
+            }
+        }
+    }
+
+    result as usize
+}
+
 fn main() {
     let contents = fs::read_to_string("input.txt").expect("Input file read failed");
     let mut lines : Vec<&str> = Vec::new();
     lines.extend(contents.split("\n").filter(|l| ! l.is_empty()));
 
-    let input_lines = parse_input_lines(&lines);
-    let line_points = generate_line_points(&input_lines);
-    let point_counts = count_line_points(&line_points);
-    let overlaps = count_overlaps(&point_counts);
+    let input_lines : Vec<Vec<u32>> = parse_input_lines(&lines).into_iter()
+        .filter(|l| l[0] == l[2] || l[1] == l[3])
+        .collect();
+    let overlaps = count_overlaps_sweep(&input_lines);
 
     println!("{}", overlaps);
 
@@ -144,4 +243,56 @@ mod tests {
 
         assert_eq!(overlaps ,5);
     }
+
+#[test]
+    fn test_sweep_example() {
+        let lines: Vec<&str> = vec![
+            "0,9 -> 5,9",
+            "8,0 -> 0,8",
+            "9,4 -> 3,4",
+            "2,2 -> 2,1",
+            "7,0 -> 7,4",
+            "6,4 -> 2,0",
+            "0,9 -> 2,9",
+            "3,4 -> 1,4",
+            "0,0 -> 8,8",
+            "5,5 -> 8,2",
+        ];
+
+        let input_lines : Vec<Vec<u32>> = parse_input_lines(&lines).into_iter().filter(|l| l[0] == l[2] || l[1] == l[3]).collect();
+        assert_eq!(count_overlaps_sweep(&input_lines), 5);
+    }
+
+#[test]
+    fn test_sweep_matches_point_expansion() {
+        let mut seed : u32 = 12345;
+        let mut random = |limit: u32| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            (seed >> 16) % limit
+        };
+
+        for _ in 0..200 {
+            let mut input_lines : Vec<Vec<u32>> = Vec::new();
+            for _ in 0..random(12) {
+                let (ax, ay) = (random(20), random(20));
+                let length = random(10) + 1;
+                // part1 only has horizontal and vertical lines
+                let (bx, by) = if random(2) == 0 { (ax + length, ay) } else { (ax, ay + length) };
+                input_lines.push(vec![ax, ay, bx, by]);
+            }
+
+            let point_counts = count_line_points(&generate_line_points(&input_lines));
+            assert_eq!(count_overlaps_sweep(&input_lines), count_overlaps(&point_counts), "{:?}", input_lines);
+        }
+    }
+
+#[test]
+    fn test_sweep_huge_coordinates() {
+        let input_lines : Vec<Vec<u32>> = vec![
+            vec![0, 5, 3000000, 5],
+            vec![1000000, 5, 4000000, 5],
+            vec![2000000, 0, 2000000, 4000000],
+        ];
+        assert_eq!(count_overlaps_sweep(&input_lines), 2000001);
+    }
 }
@@ -1,3 +1,4 @@
+use std::env;
 use std::fs;
 use std::collections::HashMap;
 
@@ -15,42 +16,85 @@ fn parse_input_lines(lines: &Vec<&str>) -> Vec<Vec<u32>> {
     result
 }
 
-fn gen_coords_between(a: u32, b: u32) -> Vec<u32> {
-    let mut result: Vec<u32> = Vec::new();
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum RasterPolicy {
+    // only horizontal, vertical and 45 degree lines are allowed
+    Strict,
+    // only the points lying exactly on the line
+    GridExact,
+    // one cell per step along the longer axis, the one closest to the line
+    Bresenham,
+}
 
-    let mut i : u32 = a;
-    while i != b {
-        result.push(i);
-        if a > b {
-            i -= 1;
-        } else {
-            i += 1;
+fn parse_raster_policy(text: &str) -> Result<RasterPolicy, String> {
+    match text {
+        "strict" => Ok(RasterPolicy::Strict),
+        "exact" => Ok(RasterPolicy::GridExact),
+        "bresenham" => Ok(RasterPolicy::Bresenham),
+        _ => Err(format!("unknown raster policy '{}'", text)),
+    }
+}
+
+fn has_puzzle_slope(line: &[u32]) -> bool {
+    line[0] == line[2] || line[1] == line[3] || line[0].abs_diff(line[2]) == line[1].abs_diff(line[3])
+}
+
+fn validate_lines(lines: &[Vec<u32>], policy: RasterPolicy) -> Result<(), String> {
+    if policy == RasterPolicy::Strict {
+        for line in lines {
+            if ! has_puzzle_slope(line) {
+                return Err(format!("line {},{} -> {},{} is neither horizontal, vertical nor diagonal", line[0], line[1], line[2], line[3]));
+            }
         }
     }
-    result.push(b);
-    
-    result
+    Ok(())
 }
 
-fn generate_line_points(lines: &Vec<Vec<u32>>) -> Vec<(u32, u32)> {
-    let mut line_points: Vec<(u32, u32)> = Vec::new();
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
 
-    for line in lines {
-        if let [mut ax, mut ay, mut bx, mut by] = line[..] {
-            let mut coords_x = gen_coords_between(ax, bx);
-            let mut coords_y = gen_coords_between(ay, by);
+fn rasterize(line: &[u32], policy: RasterPolicy) -> Vec<(u32, u32)> {
+    let (ax, ay, bx, by) = (line[0] as i64, line[1] as i64, line[2] as i64, line[3] as i64);
+    let mut result : Vec<(u32, u32)> = Vec::new();
 
-            while coords_x.len() < coords_y.len() {
-                coords_x.push(coords_x[0]);
+    if policy == RasterPolicy::Bresenham {
+        let dx = (bx - ax).abs();
+        let dy = -(by - ay).abs();
+        let (sx, sy) = ((bx - ax).signum(), (by - ay).signum());
+        let (mut x, mut y) = (ax, ay);
+        let mut error = dx + dy;
+        loop {
+            result.push((x as u32, y as u32));
+            if x == bx && y == by {
+                break;
             }
-            while coords_y.len() < coords_x.len() {
-                coords_y.push(coords_y[0]);
+            let error2 = 2*error;
+            if error2 >= dy {
+                error += dy;
+                x += sx;
             }
-
-            for (x,y) in coords_x.iter().zip(coords_y.iter()) {
-                line_points.push((*x, *y));
+            if error2 <= dx {
+                error += dx;
+                y += sy;
             }
         }
+    } else {
+        let steps = gcd((bx - ax).abs(), (by - ay).abs()).max(1);
+        let (sx, sy) = ((bx - ax) / steps, (by - ay) / steps);
+        for i in 0 ..= steps {
+            result.push(((ax + sx*i) as u32, (ay + sy*i) as u32));
+        }
+    }
+
+    result
+}
+
+fn generate_line_points(lines: &[Vec<u32>], policy: RasterPolicy) -> Vec<(u32, u32)> {
+    let mut line_points: Vec<(u32, u32)> = Vec::new();
+
+    for line in lines {
+        line_points.extend(rasterize(line, policy));
     }
 
     line_points
@@ -66,27 +110,232 @@ fn count_line_points(line_points: &Vec<(u32, u32)>) -> HashMap<(u32, u32), u32>
     result
 }
 
+fn count_covered_at_least(points: &HashMap<(u32, u32), u32>, times: u32) -> usize {
+    points.values().filter(|&&coverage| coverage >= times).count()
+}
+
 fn count_overlaps(points: &HashMap<(u32, u32), u32>) -> usize {
-    let mut result: usize = 0; 
+    count_covered_at_least(points, 2)
+}
+
+// ties are resolved towards the top left corner
+fn most_covered_cell(points: &HashMap<(u32, u32), u32>) -> Option<((u32, u32), u32)> {
+    points.iter()
+        .map(|(&(x, y), &coverage)| (coverage, std::cmp::Reverse((y, x))))
+        .max()
+        .map(|(coverage, std::cmp::Reverse((y, x)))| ((x, y), coverage))
+}
+
+fn map_size(points: &HashMap<(u32, u32), u32>) -> (u32, u32) {
+    let width = points.keys().map(|p| p.0 + 1).max().unwrap_or(0);
+    let height = points.keys().map(|p| p.1 + 1).max().unwrap_or(0);
+    (width, height)
+}
+
+fn render_ascii_heatmap(points: &HashMap<(u32, u32), u32>) -> String {
+    let (width, height) = map_size(points);
+    let mut result = String::new();
+    for y in 0..height {
+        for x in 0..width {
+            result.push(match points.get(&(x, y)) {
+                None => '.',
+                Some(&coverage) if coverage > 9 => '+',
+                Some(&coverage) => char::from_digit(coverage, 10).unwrap(),
+            });
+        }
+        result.push('\n');
+    }
+    result
+}
+
+// plain (ASCII) PGM, brighter means covered more times
+fn render_pgm(points: &HashMap<(u32, u32), u32>) -> String {
+    let (width, height) = map_size(points);
+    let max_coverage = points.values().cloned().max().unwrap_or(0).max(1);
+    let mut result = format!("P2\n{} {}\n{}\n", width, height, max_coverage);
+    for y in 0..height {
+        let row : Vec<String> = (0..width).map(|x| points.get(&(x, y)).unwrap_or(&0).to_string()).collect();
+        result.push_str(&row.join(" "));
+        result.push('\n');
+    }
+    result
+}
+
+// maps coverage to a black - red - yellow - white ramp
+fn heat_colour(coverage: u32, max_coverage: u32) -> (u32, u32, u32) {
+    let level = coverage*765 / max_coverage;
+    (level.min(255), level.saturating_sub(255).min(255), level.saturating_sub(510))
+}
+
+// plain (ASCII) PPM with a heat colour ramp
+fn render_ppm(points: &HashMap<(u32, u32), u32>) -> String {
+    let (width, height) = map_size(points);
+    let max_coverage = points.values().cloned().max().unwrap_or(0).max(1);
+    let mut result = format!("P3\n{} {}\n255\n", width, height);
+    for y in 0..height {
+        let row : Vec<String> = (0..width).map(|x| {
+            let (r, g, b) = heat_colour(*points.get(&(x, y)).unwrap_or(&0), max_coverage);
+            format!("{} {} {}", r, g, b)
+        }).collect();
+        result.push_str(&row.join(" "));
+        result.push('\n');
+    }
+    result
+}
+
+fn get_option<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    let prefix = format!("--{}=", name);
+    args.iter().find_map(|a| a.strip_prefix(&prefix))
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    x0: i64,
+    y0: i64,
+    x1: i64,
+    y1: i64,
+    slope: i64,
+}
+
+impl Segment {
+    pub fn new(line: &[u32]) -> Self {
+        let (mut ax, mut ay, mut bx, mut by) = (line[0] as i64, line[1] as i64, line[2] as i64, line[3] as i64);
+        if ax > bx || (ax == bx && ay > by) {
+            std::mem::swap(&mut ax, &mut bx);
+            std::mem::swap(&mut ay, &mut by);
+        }
+        let dx = bx - ax;
+        let dy = by - ay;
+        assert!(dx == 0 || dy == 0 || dx == dy.abs(), "unsupported slope in line {:?}", line);
+        Segment{x0: ax, y0: ay, x1: bx, y1: by, slope: if dx == 0 { 0 } else { dy / dx }}
+    }
+
+    pub fn is_vertical(&self) -> bool {
+        self.x0 == self.x1
+    }
+
+    pub fn is_diagonal(&self) -> bool {
+        self.slope != 0
+    }
+}
 
-    for (_, coverage) in points {
-        if *coverage >= 2 {
-            result += 1;
+// counts points covered at least twice within column x of the active segments
+fn count_column_overlaps(active: &[Segment], x: i64) -> i64 {
+    let mut events : Vec<(i64, i64)> = Vec::new();
+    for segment in active {
+        if x < segment.x0 || x > segment.x1 {
+            continue;
+        }
+        if segment.is_vertical() {
+            events.push((segment.y0, 1));
+            events.push((segment.y1 + 1, -1));
+        } else {
+            let y = segment.y0 + segment.slope*(x - segment.x0);
+            events.push((y, 1));
+            events.push((y + 1, -1));
         }
     }
+    events.sort();
 
+    let mut result : i64 = 0;
+    let mut coverage : i64 = 0;
+    let mut previous_y : i64 = 0;
+    for (y, delta) in events {
+        if coverage >= 2 {
+            result += y - previous_y;
+        }
+        coverage += delta;
+        previous_y = y;
+    }
     result
 }
 
+// sweeps the segments from left to right; columns between two events look the
+// same unless a diagonal is active, so those are only counted once
+fn count_overlaps_sweep(lines: &[Vec<u32>]) -> usize {
+    let mut segments : Vec<Segment> = lines.iter().map(|l| Segment::new(l)).collect();
+    segments.sort_by_key(|s| s.x0);
+
+    let mut events : Vec<i64> = segments.iter().flat_map(|s| [s.x0, s.x1 + 1]).collect();
+    events.sort();
+    events.dedup();
+
+    let mut active : Vec<Segment> = Vec::new();
+    let mut next_segment : usize = 0;
+    let mut result : i64 = 0;
+
+    for (i, &x) in events.iter().enumerate() {
+        active.retain(|s| s.x1 >= x);
+        while next_segment < segments.len() && segments[next_segment].x0 == x {
+            active.push(segments[next_segment]);
+            next_segment += 1;
+        }
+
+        result += count_column_overlaps(&active, x);
+
+        let run_end = if i + 1 < events.len() { events[i + 1] } else { x + 1 };
+        if run_end > x + 1 {
+            if active.iter().any(|s| s.is_diagonal()) {
+                for column in x + 1 .. run_end {
+                    result += count_column_overlaps(&active, column);
+                }
+            } else {
+                result += count_column_overlaps(&active, x + 1)*(run_end - x - 1);
+            }
+        }
+    }
+
+    result as usize
+}
+
 fn main() {
     let contents = fs::read_to_string("input.txt").expect("Input file read failed");
     let mut lines : Vec<&str> = Vec::new();
     lines.extend(contents.split("\n").filter(|l| ! l.is_empty()));
 
+    let args : Vec<String> = env::args().skip(1).collect();
+    let policy = match args.iter().find(|a| ! a.starts_with("--")) {
+        Some(text) => parse_raster_policy(text).expect("invalid raster policy"),
+        None => RasterPolicy::Strict,
+    };
+
     let input_lines = parse_input_lines(&lines);
-    let line_points = generate_line_points(&input_lines);
-    let point_counts = count_line_points(&line_points);
-    let overlaps = count_overlaps(&point_counts);
+    if let Err(message) = validate_lines(&input_lines, policy) {
+        panic!("invalid input: {}", message);
+    }
+
+    let wants_coverage_map = args.iter().any(|a| a == "--ascii" || a == "--most-covered")
+        || ["pgm", "ppm", "at-least"].iter().any(|name| get_option(&args, name).is_some());
+
+    if wants_coverage_map {
+        let point_counts = count_line_points(&generate_line_points(&input_lines, policy));
+        if args.iter().any(|a| a == "--ascii") {
+            print!("{}", render_ascii_heatmap(&point_counts));
+        }
+        if let Some(path) = get_option(&args, "pgm") {
+            fs::write(path, render_pgm(&point_counts)).expect("PGM file write failed");
+        }
+        if let Some(path) = get_option(&args, "ppm") {
+            fs::write(path, render_ppm(&point_counts)).expect("PPM file write failed");
+        }
+        if let Some(times) = get_option(&args, "at-least") {
+            let times : u32 = times.parse().expect("invalid coverage");
+            println!("{}", count_covered_at_least(&point_counts, times));
+        }
+        if args.iter().any(|a| a == "--most-covered") {
+            if let Some(((x, y), coverage)) = most_covered_cell(&point_counts) {
+                println!("{},{} covered {} times", x, y, coverage);
+            }
+        }
+        return;
+    }
+
+    // the sweep only understands the strict puzzle slopes
+    let overlaps = if policy == RasterPolicy::Strict {
+        count_overlaps_sweep(&input_lines)
+    } else {
+        count_overlaps(&count_line_points(&generate_line_points(&input_lines, policy)))
+    };
 
     println!("{}", overlaps);
 
@@ -103,9 +352,9 @@ mod tests {
             vec![9, 4, 3, 4], 
         ];
 
-        let line_points = generate_line_points(&input_lines);
+        let line_points = generate_line_points(&input_lines, RasterPolicy::Strict);
         let line_points_expected : Vec<(u32, u32)> = vec![
-            (3, 4), (4, 4), (5, 4), (6, 4), (7, 4), (8, 4), (9, 4)
+            (9, 4), (8, 4), (7, 4), (6, 4), (5, 4), (4, 4), (3, 4)
         ];
 
         assert_eq!(line_points, line_points_expected);
@@ -145,10 +394,144 @@ mod tests {
 
         assert_eq!(input_lines, input_lines_expected);
 
-        let line_points = generate_line_points(&input_lines);
+        let line_points = generate_line_points(&input_lines, RasterPolicy::Strict);
         let point_counts = count_line_points(&line_points);
         let overlaps = count_overlaps(&point_counts);
 
-        assert_eq!(overlaps ,5);
+        assert_eq!(overlaps ,12);
+    }
+
+#[test]
+    fn test_sweep_example() {
+        let lines: Vec<&str> = vec![
+            "0,9 -> 5,9",
+            "8,0 -> 0,8",
+            "9,4 -> 3,4",
+            "2,2 -> 2,1",
+            "7,0 -> 7,4",
+            "6,4 -> 2,0",
+            "0,9 -> 2,9",
+            "3,4 -> 1,4",
+            "0,0 -> 8,8",
+            "5,5 -> 8,2",
+        ];
+
+        let input_lines : Vec<Vec<u32>> = parse_input_lines(&lines).into_iter().collect();
+        assert_eq!(count_overlaps_sweep(&input_lines), 12);
+    }
+
+#[test]
+    fn test_sweep_matches_point_expansion() {
+        let mut seed : u32 = 12345;
+        let mut random = |limit: u32| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            (seed >> 16) % limit
+        };
+
+        for _ in 0..200 {
+            let mut input_lines : Vec<Vec<u32>> = Vec::new();
+            for _ in 0..random(12) {
+                let (ax, ay) = (random(20), random(20));
+                let length = random(10) + 1;
+                let (bx, by) = match random(4) {
+                    0 => (ax + length, ay),
+                    1 => (ax, ay + length),
+                    2 => (ax + length, ay + length),
+                    _ => (ax + length, ay.saturating_sub(length)),
+                };
+                if by == ay || bx == ax || bx - ax == ay.abs_diff(by) {
+                    input_lines.push(vec![ax, ay, bx, by]);
+                }
+            }
+
+            let point_counts = count_line_points(&generate_line_points(&input_lines, RasterPolicy::Strict));
+            assert_eq!(count_overlaps_sweep(&input_lines), count_overlaps(&point_counts), "{:?}", input_lines);
+        }
+    }
+
+#[test]
+    fn test_sweep_huge_coordinates() {
+        let input_lines : Vec<Vec<u32>> = vec![
+            vec![0, 5, 3000000, 5],
+            vec![1000000, 5, 4000000, 5],
+            vec![2000000, 0, 2000000, 4000000],
+        ];
+        assert_eq!(count_overlaps_sweep(&input_lines), 2000001);
+    }
+
+#[test]
+    fn test_rasterize_policies() {
+        assert_eq!(rasterize(&[0, 0, 4, 2], RasterPolicy::GridExact), vec![(0, 0), (2, 1), (4, 2)]);
+        assert_eq!(rasterize(&[0, 0, 4, 2], RasterPolicy::Bresenham), vec![(0, 0), (1, 1), (2, 1), (3, 2), (4, 2)]);
+        assert_eq!(rasterize(&[3, 0, 0, 1], RasterPolicy::GridExact), vec![(3, 0), (0, 1)]);
+        assert_eq!(rasterize(&[3, 0, 0, 1], RasterPolicy::Bresenham), vec![(3, 0), (2, 0), (1, 1), (0, 1)]);
+        assert_eq!(rasterize(&[5, 5, 5, 5], RasterPolicy::Bresenham), vec![(5, 5)]);
+
+        for line in [[0, 0, 3, 3], [3, 0, 0, 3], [2, 7, 2, 4], [1, 1, 4, 1]] {
+            let expected = rasterize(&line, RasterPolicy::Strict);
+            assert_eq!(rasterize(&line, RasterPolicy::GridExact), expected);
+            assert_eq!(rasterize(&line, RasterPolicy::Bresenham), expected);
+        }
+    }
+
+#[test]
+    fn test_validate_lines() {
+        let input_lines : Vec<Vec<u32>> = vec![vec![0, 0, 3, 3], vec![0, 0, 4, 2]];
+        assert_eq!(validate_lines(&input_lines, RasterPolicy::Strict).unwrap_err(), "line 0,0 -> 4,2 is neither horizontal, vertical nor diagonal");
+        assert!(validate_lines(&input_lines, RasterPolicy::GridExact).is_ok());
+        assert!(validate_lines(&input_lines[..1], RasterPolicy::Strict).is_ok());
+    }
+
+    fn example_point_counts() -> HashMap<(u32, u32), u32> {
+        let lines: Vec<&str> = vec![
+            "0,9 -> 5,9",
+            "8,0 -> 0,8",
+            "9,4 -> 3,4",
+            "2,2 -> 2,1",
+            "7,0 -> 7,4",
+            "6,4 -> 2,0",
+            "0,9 -> 2,9",
+            "3,4 -> 1,4",
+            "0,0 -> 8,8",
+            "5,5 -> 8,2",
+        ];
+        count_line_points(&generate_line_points(&parse_input_lines(&lines), RasterPolicy::Strict))
+    }
+
+#[test]
+    fn test_coverage_queries() {
+        let point_counts = example_point_counts();
+        assert_eq!(count_covered_at_least(&point_counts, 1), 39);
+        assert_eq!(count_covered_at_least(&point_counts, 2), 12);
+        assert_eq!(count_covered_at_least(&point_counts, 3), 2);
+        assert_eq!(most_covered_cell(&point_counts), Some(((4, 4), 3)));
+        assert_eq!(most_covered_cell(&HashMap::new()), None);
+    }
+
+#[test]
+    fn test_render_ascii_heatmap() {
+        let expected = "\
+1.1....11.
+.111...2..
+..2.1.111.
+...1.2.2..
+.112313211
+...1.2....
+..1...1...
+.1.....1..
+1.......1.
+222111....
+";
+        assert_eq!(render_ascii_heatmap(&example_point_counts()), expected);
+    }
+
+#[test]
+    fn test_render_images() {
+        let mut point_counts : HashMap<(u32, u32), u32> = HashMap::new();
+        point_counts.insert((0, 0), 2);
+        point_counts.insert((2, 1), 1);
+
+        assert_eq!(render_pgm(&point_counts), "P2\n3 2\n2\n2 0 0\n0 0 1\n");
+        assert_eq!(render_ppm(&point_counts), "P3\n3 2\n255\n255 255 255 0 0 0 0 0 0\n0 0 0 0 0 0 255 127 0\n");
     }
 }
@@ -1,45 +1,237 @@
+use std::env;
 use std::fs;
+use num_bigint::BigUint;
+use num_traits::{CheckedAdd, CheckedMul, One, Zero};
 
-const DAYS_TO_BREED : usize = 8;
-const AGE_ARRAY_LEN : usize = DAYS_TO_BREED + 1;
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct PopulationModel {
+    breeding_cycle: usize,
+    newborn_delay: usize,
+}
+
+const PUZZLE_MODEL : PopulationModel = PopulationModel{breeding_cycle: 7, newborn_delay: 2};
+
+impl PopulationModel {
+    pub fn age_array_len(&self) -> usize {
+        self.breeding_cycle + self.newborn_delay
+    }
+
+    pub fn reset_age(&self) -> usize {
+        self.breeding_cycle - 1
+    }
+
+    pub fn newborn_age(&self) -> usize {
+        self.age_array_len() - 1
+    }
+}
+
+fn parse_input_numbers(input: &str, model: &PopulationModel) -> Result<Vec<usize>, String> {
+    let mut ages : Vec<usize> = vec![0; model.age_array_len()];
+
+    for s in input.trim().split(',') {
+        let age : usize = s.parse().map_err(|_| format!("invalid age '{}'", s))?;
+        if age >= ages.len() {
+            return Err(format!("age {} does not fit the model, maximum is {}", age, ages.len() - 1));
+        }
+        ages[age] += 1;
+    }
+
+    Ok(ages)
+}
+
+fn simulate_a_day<T: Clone + Zero + CheckedAdd>(model: &PopulationModel, today: &[T]) -> Option<Vec<T>> {
+    let mut tomorrow : Vec<T> = vec![T::zero(); today.len()];
+
+    tomorrow[model.reset_age()] = tomorrow[model.reset_age()].checked_add(&today[0])?;
+    tomorrow[model.newborn_age()] = tomorrow[model.newborn_age()].checked_add(&today[0])?;
+    for i in 1..today.len() {
+        tomorrow[i-1] = tomorrow[i-1].checked_add(&today[i])?;
+    }
+
+    Some(tomorrow)
+}
+
+fn count_fish_after(model: &PopulationModel, ages: &[usize], days: usize) -> usize {
+    let mut current_day = ages.to_vec();
+
+    for _ in 0..days {
+        current_day = simulate_a_day(model, &current_day).expect("fish count overflow");
+    }
+
+    current_day.iter().sum()
+}
+
+// age buckets for every day from 0 to days inclusive
+fn population_series(model: &PopulationModel, ages: &[usize], days: usize) -> Vec<Vec<BigUint>> {
+    let mut result : Vec<Vec<BigUint>> = vec![ages.iter().map(|&count| BigUint::from(count)).collect()];
 
-fn parse_input_numbers(input: &str) -> [usize; AGE_ARRAY_LEN] {
-    let mut fishes : Vec<usize> = Vec::new();
+    for _ in 0..days {
+        let tomorrow = simulate_a_day(model, &result[result.len()-1]).unwrap();
+        result.push(tomorrow);
+    }
 
-    fishes.extend(input.trim().split(",").map(|s| s.parse::<usize>().unwrap()));
+    result
+}
 
-    let mut ages : [usize; AGE_ARRAY_LEN] = [0; AGE_ARRAY_LEN];
+fn series_to_csv(series: &[Vec<BigUint>]) -> String {
+    let buckets = series[0].len();
+    let mut result = String::from("day");
+    for age in 0..buckets {
+        result.push_str(&format!(",age{}", age));
+    }
+    result.push_str(",total\n");
 
-    for i in 0..AGE_ARRAY_LEN {
-        ages[i] = fishes.iter().filter(|age| **age == i).count();
+    for (day, ages) in series.iter().enumerate() {
+        result.push_str(&day.to_string());
+        for count in ages {
+            result.push_str(&format!(",{}", count));
+        }
+        result.push_str(&format!(",{}\n", ages.iter().sum::<BigUint>()));
     }
 
-    ages
+    result
 }
 
-fn simulate_a_day(today: [usize; AGE_ARRAY_LEN]) -> [usize; AGE_ARRAY_LEN] {
-    let mut tomorrow : [usize; AGE_ARRAY_LEN] = [0; AGE_ARRAY_LEN];
+// the transition has characteristic polynomial x^len - x^(len - cycle) - 1,
+// its only root above 1 is the dominant eigenvalue
+fn dominant_eigenvalue(model: &PopulationModel) -> f64 {
+    let len = model.age_array_len() as i32;
+    let cycle = model.breeding_cycle as i32;
+    let polynomial = |x: f64| x.powi(len) - x.powi(len - cycle) - 1.0;
 
-    tomorrow[6] = today[0];
-    tomorrow[8] = today[0];
-    for i in 1..AGE_ARRAY_LEN {
-        tomorrow[i-1] += today[i];
+    let (mut low, mut high) = (1.0, 2.0);
+    for _ in 0..100 {
+        let middle = (low + high) / 2.0;
+        if polynomial(middle) < 0.0 {
+            low = middle;
+        } else {
+            high = middle;
+        }
     }
 
-    tomorrow
+    (low + high) / 2.0
+}
+
+fn doubling_time(model: &PopulationModel) -> f64 {
+    2f64.ln() / dominant_eigenvalue(model).ln()
+}
+
+type Matrix<T> = Vec<Vec<T>>;
+
+// row i holds the ages a fish of age i turns into on the next day
+fn transition_matrix<T: Clone + Zero + One + CheckedAdd>(model: &PopulationModel) -> Matrix<T> {
+    let len = model.age_array_len();
+    let mut result : Matrix<T> = vec![vec![T::zero(); len]; len];
+
+    for i in 1..len {
+        result[i][i-1] = T::one();
+    }
+    result[0][model.reset_age()] = result[0][model.reset_age()].checked_add(&T::one()).unwrap();
+    result[0][model.newborn_age()] = result[0][model.newborn_age()].checked_add(&T::one()).unwrap();
+
+    result
+}
+
+fn matrix_multiply<T: Clone + Zero + CheckedAdd + CheckedMul>(a: &Matrix<T>, b: &Matrix<T>) -> Option<Matrix<T>> {
+    let len = a.len();
+    let mut result : Matrix<T> = vec![vec![T::zero(); len]; len];
+
+    for i in 0..len {
+        for k in 0..len {
+            if a[i][k].is_zero() {
+                continue;
+            }
+            for j in 0..len {
+                let product = a[i][k].checked_mul(&b[k][j])?;
+                result[i][j] = result[i][j].checked_add(&product)?;
+            }
+        }
+    }
+
+    Some(result)
+}
+
+// square and multiply, None when the counts overflow T
+fn matrix_power<T: Clone + Zero + One + CheckedAdd + CheckedMul>(matrix: &Matrix<T>, mut exponent: u64) -> Option<Matrix<T>> {
+    let len = matrix.len();
+    let mut result : Matrix<T> = vec![vec![T::zero(); len]; len];
+    for (i, row) in result.iter_mut().enumerate() {
+        row[i] = T::one();
+    }
+
+    let mut base = matrix.clone();
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = matrix_multiply(&result, &base)?;
+        }
+        exponent >>= 1;
+        if exponent > 0 {
+            base = matrix_multiply(&base, &base)?;
+        }
+    }
+
+    Some(result)
+}
+
+fn count_fish_fast<T: Clone + Zero + One + CheckedAdd + CheckedMul + From<u64>>(model: &PopulationModel, ages: &[usize], days: u64) -> Option<T> {
+    let power = matrix_power(&transition_matrix::<T>(model), days)?;
+
+    let mut result = T::zero();
+    for (i, &count) in ages.iter().enumerate() {
+        let count = T::from(count as u64);
+        for value in &power[i] {
+            result = result.checked_add(&count.checked_mul(value)?)?;
+        }
+    }
+
+    Some(result)
+}
+
+fn count_fish_u128(model: &PopulationModel, ages: &[usize], days: u64) -> Result<u128, String> {
+    count_fish_fast::<u128>(model, ages, days).ok_or(format!("fish count after {} days does not fit into 128 bits", days))
+}
+
+fn count_fish_big(model: &PopulationModel, ages: &[usize], days: u64) -> BigUint {
+    count_fish_fast::<BigUint>(model, ages, days).unwrap()
+}
+
+fn get_option<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    let prefix = format!("--{}=", name);
+    args.iter().find_map(|a| a.strip_prefix(&prefix))
 }
 
 fn main() {
     let input = fs::read_to_string("input.txt").expect("failed to load input file");
-    let mut current_day = parse_input_numbers(&input);
+    let ages = match parse_input_numbers(&input, &PUZZLE_MODEL) {
+        Ok(ages) => ages,
+        Err(message) => panic!("invalid input: {}", message),
+    };
 
-    for _ in 0..256 {
-        current_day = simulate_a_day(current_day);
+    let args : Vec<String> = env::args().skip(1).collect();
+
+    if let Some(days) = get_option(&args, "csv") {
+        let days : usize = days.parse().expect("invalid number of days");
+        print!("{}", series_to_csv(&population_series(&PUZZLE_MODEL, &ages, days)));
+        return;
+    }
+
+    if args.iter().any(|a| a == "--stats") {
+        println!("dominant eigenvalue {:.6}", dominant_eigenvalue(&PUZZLE_MODEL));
+        println!("doubling time {:.3} days", doubling_time(&PUZZLE_MODEL));
+        return;
     }
 
-    let final_count : usize = current_day.iter().sum();
+    if let Some(days) = args.iter().find(|a| ! a.starts_with("--")) {
+        let days : u64 = days.parse().expect("invalid number of days");
+        match count_fish_u128(&PUZZLE_MODEL, &ages, days) {
+            Ok(count) => println!("{}", count),
+            Err(_) => println!("{}", count_fish_big(&PUZZLE_MODEL, &ages, days)),
+        }
+        return;
+    }
 
-    println!("{}", final_count);
+    println!("{}", count_fish_after(&PUZZLE_MODEL, &ages, 80));
+    println!("{}", count_fish_u128(&PUZZLE_MODEL, &ages, 256).unwrap());
 }
 
 #[cfg(test)]
@@ -49,8 +241,93 @@ mod tests {
 #[test]
     fn test_parse_input_numbers() {
         let input = "4,3,4,5,2,1,1,5,5";
-        let actual_result = parse_input_numbers(input);
+        let actual_result = parse_input_numbers(input, &PUZZLE_MODEL).unwrap();
         let expected_result = [0, 2, 1, 1, 2, 3, 0, 0, 0];
         assert_eq!(actual_result, expected_result);
     }
+
+#[test]
+    fn test_parse_input_numbers_out_of_range() {
+        assert_eq!(parse_input_numbers("3,9", &PUZZLE_MODEL).unwrap_err(), "age 9 does not fit the model, maximum is 8");
+        assert!(parse_input_numbers("3,x", &PUZZLE_MODEL).is_err());
+
+        let slow_model = PopulationModel{breeding_cycle: 9, newborn_delay: 3};
+        assert_eq!(parse_input_numbers("3,9", &slow_model).unwrap().len(), 12);
+    }
+
+#[test]
+    fn test_count_fish_upstream_example() {
+        let ages = parse_input_numbers("3,4,3,1,2", &PUZZLE_MODEL).unwrap();
+        assert_eq!(count_fish_after(&PUZZLE_MODEL, &ages, 18), 26);
+        assert_eq!(count_fish_after(&PUZZLE_MODEL, &ages, 80), 5934);
+        assert_eq!(count_fish_after(&PUZZLE_MODEL, &ages, 256), 26984457539);
+    }
+
+#[test]
+    fn test_custom_model() {
+        // a single fish splits every day when both cycle and delay are minimal
+        let model = PopulationModel{breeding_cycle: 1, newborn_delay: 0};
+        let ages = parse_input_numbers("0", &model).unwrap();
+        assert_eq!(count_fish_after(&model, &ages, 10), 1024);
+    }
+
+#[test]
+    fn test_count_fish_fast_matches_simulation() {
+        let ages = parse_input_numbers("3,4,3,1,2", &PUZZLE_MODEL).unwrap();
+        for days in 0..300 {
+            assert_eq!(count_fish_u128(&PUZZLE_MODEL, &ages, days as u64).unwrap(), count_fish_after(&PUZZLE_MODEL, &ages, days) as u128);
+        }
+
+        let model = PopulationModel{breeding_cycle: 3, newborn_delay: 4};
+        let ages = parse_input_numbers("0,6,2", &model).unwrap();
+        for days in 0..100 {
+            assert_eq!(count_fish_u128(&model, &ages, days as u64).unwrap(), count_fish_after(&model, &ages, days) as u128);
+        }
+    }
+
+#[test]
+    fn test_count_fish_overflow() {
+        let ages = parse_input_numbers("3,4,3,1,2", &PUZZLE_MODEL).unwrap();
+        assert!(count_fish_u128(&PUZZLE_MODEL, &ages, 900).is_ok());
+        assert!(count_fish_u128(&PUZZLE_MODEL, &ages, 2000).is_err());
+        assert_eq!(count_fish_big(&PUZZLE_MODEL, &ages, 900), BigUint::from(count_fish_u128(&PUZZLE_MODEL, &ages, 900).unwrap()));
+    }
+
+#[test]
+    fn test_count_fish_big_recurrence() {
+        // totals follow the characteristic polynomial x^9 = x^2 + 1
+        let ages = parse_input_numbers("3,4,3,1,2", &PUZZLE_MODEL).unwrap();
+        let days = 20000;
+        assert_eq!(count_fish_big(&PUZZLE_MODEL, &ages, days),
+            count_fish_big(&PUZZLE_MODEL, &ages, days - 7) + count_fish_big(&PUZZLE_MODEL, &ages, days - 9));
+    }
+
+#[test]
+    fn test_series_to_csv() {
+        let ages = parse_input_numbers("3,4,3,1,2", &PUZZLE_MODEL).unwrap();
+        let series = population_series(&PUZZLE_MODEL, &ages, 18);
+        let csv = series_to_csv(&series);
+        let lines : Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines.len(), 20);
+        assert_eq!(lines[0], "day,age0,age1,age2,age3,age4,age5,age6,age7,age8,total");
+        assert_eq!(lines[1], "0,0,1,1,2,1,0,0,0,0,5");
+        assert_eq!(lines[2], "1,1,1,2,1,0,0,0,0,0,5");
+        assert_eq!(lines[3], "2,1,2,1,0,0,0,1,0,1,6");
+        assert!(lines[19].ends_with(",26"));
+    }
+
+#[test]
+    fn test_growth_statistics() {
+        let eigenvalue = dominant_eigenvalue(&PUZZLE_MODEL);
+        assert!((eigenvalue.powi(9) - eigenvalue.powi(2) - 1.0).abs() < 1e-9);
+
+        let ages = parse_input_numbers("3,4,3,1,2", &PUZZLE_MODEL).unwrap();
+        let observed = count_fish_u128(&PUZZLE_MODEL, &ages, 901).unwrap() as f64 / count_fish_u128(&PUZZLE_MODEL, &ages, 900).unwrap() as f64;
+        assert!((observed - eigenvalue).abs() < 1e-4);
+
+        // a fish that splits every day doubles every day
+        let model = PopulationModel{breeding_cycle: 1, newborn_delay: 0};
+        assert!((doubling_time(&model) - 1.0).abs() < 1e-9);
+    }
 }
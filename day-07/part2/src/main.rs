@@ -1,12 +1,13 @@
+use std::env;
 use std::fs;
 
 fn parse_positions(input: &str) -> Vec<usize> {
     let mut result : Vec<usize> = Vec::new();
-    result.extend(input.split(",").map(|s| s.parse::<usize>().unwrap()));
+    result.extend(input.split(',').map(|s| s.parse::<usize>().unwrap()));
     result
 }
 
-fn find_available_range(positions: &Vec<usize>) -> (usize, usize) {
+fn find_available_range(positions: &[usize]) -> (usize, usize) {
     let min = *positions.iter().min().unwrap();
     let max = *positions.iter().max().unwrap();
 
@@ -17,30 +18,427 @@ fn get_fuel_for_distance(distance: usize) -> usize {
     distance * (distance+1) / 2
 }
 
-fn get_fuel_required_for_position(submarines: &Vec<usize>, position: usize) -> usize {
+fn get_fuel_required_for_position(submarines: &[usize], position: usize) -> usize {
     let mut result : usize = 0;
     for submarine in submarines {
-        result += get_fuel_for_distance(((position as i32) - (*submarine as i32)).abs() as usize);
+        result += get_fuel_for_distance(position.abs_diff(*submarine));
     }
 
     result
 }
 
+#[cfg(test)]
+fn find_best_position_brute_force(positions: &[usize]) -> (usize, usize) {
+    let (range_min, range_size) = find_available_range(positions);
+
+    let mut best_position = range_min;
+    let mut best_price = get_fuel_required_for_position(positions, range_min);
+
+    for position in range_min..=range_min+range_size {
+        let price = get_fuel_required_for_position(positions, position);
+        if price < best_price {
+            best_position = position;
+            best_price = price;
+        }
+    }
+
+    (best_position, best_price)
+}
+
+// the fuel is convex and its real minimum lies within 1/2 of the mean, so
+// the best integer position is among the few around the mean
+fn find_best_position(positions: &[usize]) -> (usize, usize) {
+    let (range_min, range_size) = find_available_range(positions);
+    let mean = positions.iter().sum::<usize>() / positions.len();
+
+    let first = mean.saturating_sub(1).max(range_min);
+    let last = (mean + 2).min(range_min + range_size);
+
+    (first..=last)
+        .map(|position| (position, get_fuel_required_for_position(positions, position)))
+        .min_by_key(|&(_, fuel)| fuel)
+        .unwrap()
+}
+
+trait FuelCost {
+    fn fuel(&self, crab: usize, distance: usize) -> usize;
+
+    // convex costs let the minimizer search instead of trying every position
+    fn is_convex(&self) -> bool {
+        false
+    }
+
+    // the farthest a crab can move, None when there is no limit
+    fn max_distance(&self, _crab: usize) -> Option<usize> {
+        None
+    }
+
+    // Some((a, b, c)) when every crab burns (a*d*d + b*d) / c, which lets the fuel
+    // of a whole run of crabs come from prefix sums
+    fn polynomial(&self) -> Option<(u128, u128, u128)> {
+        None
+    }
+}
+
+struct Linear;
+
+impl FuelCost for Linear {
+    fn fuel(&self, _crab: usize, distance: usize) -> usize {
+        distance
+    }
+
+    fn is_convex(&self) -> bool {
+        true
+    }
+
+    fn polynomial(&self) -> Option<(u128, u128, u128)> {
+        Some((0, 1, 1))
+    }
+}
+
+struct Triangular;
+
+impl FuelCost for Triangular {
+    fn fuel(&self, _crab: usize, distance: usize) -> usize {
+        distance * (distance+1) / 2
+    }
+
+    fn is_convex(&self) -> bool {
+        true
+    }
+
+    fn polynomial(&self) -> Option<(u128, u128, u128)> {
+        Some((1, 1, 2))
+    }
+}
+
+struct Quadratic;
+
+impl FuelCost for Quadratic {
+    fn fuel(&self, _crab: usize, distance: usize) -> usize {
+        distance.saturating_mul(distance)
+    }
+
+    fn is_convex(&self) -> bool {
+        true
+    }
+
+    fn polynomial(&self) -> Option<(u128, u128, u128)> {
+        Some((1, 0, 1))
+    }
+}
+
+// costs[crab][distance], distances past the end of a table are unreachable
+struct CostTable {
+    costs: Vec<Vec<usize>>,
+    convex: bool,
+}
+
+impl CostTable {
+    pub fn new(costs: Vec<Vec<usize>>) -> Self {
+        // f(|x - p|) is convex in x when f is nondecreasing and convex
+        let convex = costs.iter().all(|table| {
+            ! table.is_empty() && table.windows(2).all(|w| w[0] <= w[1]) && table.windows(3).all(|w| w[1] - w[0] <= w[2] - w[1])
+        });
+        CostTable{costs, convex}
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut costs : Vec<Vec<usize>> = Vec::new();
+        for (i, line) in text.lines().filter(|l| ! l.is_empty()).enumerate() {
+            let table : Result<Vec<usize>, _> = line.split(',').map(|s| s.trim().parse::<usize>()).collect();
+            costs.push(table.map_err(|_| format!("invalid cost table on line {}", i + 1))?);
+        }
+        Ok(CostTable::new(costs))
+    }
+}
+
+impl FuelCost for CostTable {
+    fn fuel(&self, crab: usize, distance: usize) -> usize {
+        self.costs[crab].get(distance).copied().unwrap_or(usize::MAX)
+    }
+
+    fn is_convex(&self) -> bool {
+        self.convex
+    }
+
+    fn max_distance(&self, crab: usize) -> Option<usize> {
+        self.costs[crab].len().checked_sub(1)
+    }
+}
+
+fn parse_fuel_cost(name: &str) -> Result<Box<dyn FuelCost>, String> {
+    match name {
+        "linear" => Ok(Box::new(Linear)),
+        "triangular" => Ok(Box::new(Triangular)),
+        "quadratic" => Ok(Box::new(Quadratic)),
+        _ => Err(format!("unknown fuel cost '{}'", name)),
+    }
+}
+
+fn total_fuel_for(cost: &dyn FuelCost, positions: &[usize], crabs: &[usize], target: usize) -> usize {
+    crabs.iter()
+        .fold(0, |total, &crab| total.saturating_add(cost.fuel(crab, positions[crab].abs_diff(target))))
+}
+
+fn total_fuel(cost: &dyn FuelCost, positions: &[usize], target: usize) -> usize {
+    let crabs : Vec<usize> = (0..positions.len()).collect();
+    total_fuel_for(cost, positions, &crabs, target)
+}
+
+// positions every one of the crabs can reach, unreachable targets cost usize::MAX
+// and would look like a flat minimum to the convex search
+fn reachable_range(cost: &dyn FuelCost, positions: &[usize], crabs: &[usize], range: (usize, usize)) -> Option<(usize, usize)> {
+    let (mut low, mut high) = range;
+    for &crab in crabs {
+        if let Some(reach) = cost.max_distance(crab) {
+            low = low.max(positions[crab].saturating_sub(reach));
+            high = high.min(positions[crab].saturating_add(reach));
+        }
+    }
+    if low <= high { Some((low, high)) } else { None }
+}
+
+// returns the lowest position with minimal total fuel for the given crabs
+fn minimize_fuel_for(cost: &dyn FuelCost, positions: &[usize], crabs: &[usize]) -> (usize, usize) {
+    let crab_positions : Vec<usize> = crabs.iter().map(|&crab| positions[crab]).collect();
+    let (range_min, range_size) = find_available_range(&crab_positions);
+    let range_max = range_min + range_size;
+    let fuel = |target: usize| total_fuel_for(cost, positions, crabs, target);
+
+    let reachable = reachable_range(cost, positions, crabs, (range_min, range_max));
+    if let (true, Some((mut low, mut high))) = (cost.is_convex(), reachable) {
+        // first position where the fuel stops decreasing
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if fuel(middle + 1) >= fuel(middle) {
+                high = middle;
+            } else {
+                low = middle + 1;
+            }
+        }
+        (low, fuel(low))
+    } else {
+        (range_min..=range_max)
+            .map(|position| (position, fuel(position)))
+            .min_by_key(|&(_, fuel)| fuel)
+            .unwrap()
+    }
+}
+
+fn minimize_fuel(cost: &dyn FuelCost, positions: &[usize]) -> (usize, usize) {
+    let crabs : Vec<usize> = (0..positions.len()).collect();
+    minimize_fuel_for(cost, positions, &crabs)
+}
+
+// all the positions sharing the minimal total fuel
+fn optimal_positions(cost: &dyn FuelCost, positions: &[usize]) -> (Vec<usize>, usize) {
+    let (best, best_fuel) = minimize_fuel(cost, positions);
+    let (range_min, range_size) = find_available_range(positions);
+    let range_max = range_min + range_size;
+
+    let crabs : Vec<usize> = (0..positions.len()).collect();
+    let reachable = reachable_range(cost, positions, &crabs, (range_min, range_max));
+    if let (true, Some((_, reachable_max))) = (cost.is_convex(), reachable) {
+        // ties of a convex function form a single run starting at best
+        let (mut low, mut high) = (best, reachable_max);
+        while low < high {
+            let middle = low + (high - low).div_ceil(2);
+            if total_fuel(cost, positions, middle) == best_fuel {
+                low = middle;
+            } else {
+                high = middle - 1;
+            }
+        }
+        ((best..=low).collect(), best_fuel)
+    } else {
+        let result = (range_min..=range_max).filter(|&position| total_fuel(cost, positions, position) == best_fuel).collect();
+        (result, best_fuel)
+    }
+}
+
+fn fuel_bill(cost: &dyn FuelCost, positions: &[usize], target: usize) -> Vec<usize> {
+    positions.iter().enumerate().map(|(crab, position)| cost.fuel(crab, position.abs_diff(target))).collect()
+}
+
+// prefix sums over sorted positions, to get the fuel of any run of crabs
+// to a target in logarithmic time
+struct RunFuel {
+    sorted: Vec<usize>,
+    sums: Vec<u128>,
+    squares: Vec<u128>,
+}
+
+impl RunFuel {
+    pub fn new(sorted: Vec<usize>) -> Self {
+        let mut sums : Vec<u128> = vec![0];
+        let mut squares : Vec<u128> = vec![0];
+        for &position in &sorted {
+            let position = position as u128;
+            sums.push(sums.last().unwrap() + position);
+            squares.push(squares.last().unwrap() + position * position);
+        }
+        RunFuel{sorted, sums, squares}
+    }
+
+    // sums of d and of d*d over the crabs sorted[start..end]
+    fn distances(&self, start: usize, end: usize, target: usize) -> (u128, u128) {
+        let split = start + self.sorted[start..end].partition_point(|&position| position < target);
+        let t = target as u128;
+        let (below, above) = ((split - start) as u128, (end - split) as u128);
+        let linear = below * t - (self.sums[split] - self.sums[start]) + (self.sums[end] - self.sums[split]) - above * t;
+        let sum = self.sums[end] - self.sums[start];
+        let square = self.squares[end] - self.squares[start] + (end - start) as u128 * t * t - 2 * t * sum;
+        (linear, square)
+    }
+
+    fn fuel(&self, polynomial: (u128, u128, u128), start: usize, end: usize, target: usize) -> usize {
+        let (a, b, c) = polynomial;
+        let (linear, square) = self.distances(start, end, target);
+        ((a * square + b * linear) / c).min(usize::MAX as u128) as usize
+    }
+
+    // lowest target with minimal fuel for sorted[start..end]
+    fn minimize(&self, polynomial: (u128, u128, u128), start: usize, end: usize) -> (usize, usize) {
+        let fuel = |target: usize| self.fuel(polynomial, start, end, target);
+        let (mut low, mut high) = (self.sorted[start], self.sorted[end - 1]);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if fuel(middle + 1) >= fuel(middle) {
+                high = middle;
+            } else {
+                low = middle + 1;
+            }
+        }
+        (low, fuel(low))
+    }
+}
+
+// costs without a closed form minimize each run from scratch, which is cubic in the crab count
+const MAX_TABLE_GROUP_CRABS : usize = 200;
+
+#[derive(Debug, PartialEq)]
+struct Alignment {
+    targets: Vec<usize>,
+    groups: Vec<usize>,
+    fuel: usize,
+}
+
+// with a convex cost the optimal groups are runs of crabs sorted by position,
+// so they can be found by dynamic programming over the sorted order
+fn align_groups(cost: &dyn FuelCost, positions: &[usize], group_count: usize) -> Result<Alignment, String> {
+    if ! cost.is_convex() {
+        return Err("aligning into groups needs a convex fuel cost".to_string());
+    }
+    if group_count == 0 || group_count > positions.len() {
+        return Err(format!("cannot split {} crabs into {} groups", positions.len(), group_count));
+    }
+
+    let crab_count = positions.len();
+    let mut order : Vec<usize> = (0..crab_count).collect();
+    order.sort_by_key(|&crab| positions[crab]);
+
+    // segment[i][j] is the best (target, fuel) for order[i..=j]
+    let mut segment : Vec<Vec<(usize, usize)>> = vec![Vec::new(); crab_count];
+    if let Some(polynomial) = cost.polynomial() {
+        let run_fuel = RunFuel::new(order.iter().map(|&crab| positions[crab]).collect());
+        for (i, runs) in segment.iter_mut().enumerate() {
+            for j in i..crab_count {
+                runs.push(run_fuel.minimize(polynomial, i, j + 1));
+            }
+        }
+    } else {
+        if crab_count > MAX_TABLE_GROUP_CRABS {
+            return Err(format!("aligning more than {} crabs into groups needs a linear, triangular or quadratic cost", MAX_TABLE_GROUP_CRABS));
+        }
+        for (i, runs) in segment.iter_mut().enumerate() {
+            for j in i..crab_count {
+                runs.push(minimize_fuel_for(cost, positions, &order[i..=j]));
+            }
+        }
+    }
+
+    // best[g][j] is the cheapest split of the first j crabs into g groups
+    let mut best : Vec<Vec<usize>> = vec![vec![usize::MAX; crab_count + 1]; group_count + 1];
+    let mut split : Vec<Vec<usize>> = vec![vec![0; crab_count + 1]; group_count + 1];
+    best[0][0] = 0;
+    for g in 1..=group_count {
+        for j in g..=crab_count {
+            for i in g-1..j {
+                if best[g-1][i] == usize::MAX {
+                    continue;
+                }
+                let fuel = best[g-1][i].saturating_add(segment[i][j-1-i].1);
+                if fuel < best[g][j] {
+                    best[g][j] = fuel;
+                    split[g][j] = i;
+                }
+            }
+        }
+    }
+
+    let mut targets : Vec<usize> = vec![0; group_count];
+    let mut groups : Vec<usize> = vec![0; crab_count];
+    let mut end = crab_count;
+    for g in (1..=group_count).rev() {
+        let start = split[g][end];
+        targets[g-1] = segment[start][end-1-start].0;
+        for &crab in &order[start..end] {
+            groups[crab] = g-1;
+        }
+        end = start;
+    }
+
+    Ok(Alignment{targets, groups, fuel: best[group_count][crab_count]})
+}
+
+fn get_option<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    let prefix = format!("--{}=", name);
+    args.iter().find_map(|a| a.strip_prefix(&prefix))
+}
+
 fn main() {
     let input = String::from(fs::read_to_string("input.txt").expect("failed to read input").trim());
     let positions = parse_positions(&input);
 
-    let (range_min, range_size) = find_available_range(&positions);
+    let args : Vec<String> = env::args().skip(1).collect();
 
-    let mut best_price = get_fuel_required_for_position(&positions, range_min);
+    let cost : Option<Box<dyn FuelCost>> = if let Some(path) = get_option(&args, "table") {
+        let text = fs::read_to_string(path).expect("failed to read cost table");
+        let table = CostTable::parse(&text).expect("invalid cost table");
+        assert_eq!(table.costs.len(), positions.len(), "cost table needs one line per crab");
+        Some(Box::new(table))
+    } else {
+        get_option(&args, "cost").map(|name| parse_fuel_cost(name).expect("invalid fuel cost"))
+    };
 
-    for position in range_min..range_min+range_size {
-        let price = get_fuel_required_for_position(&positions, position);
-        if price < best_price {
-            best_price = price;
+    if let Some(group_count) = get_option(&args, "groups") {
+        let group_count : usize = group_count.parse().expect("invalid number of groups");
+        let cost = cost.unwrap_or(Box::new(Triangular));
+        let alignment = align_groups(cost.as_ref(), &positions, group_count).expect("cannot align crabs");
+        println!("targets {:?}", alignment.targets);
+        println!("{}", alignment.fuel);
+        return;
+    }
+
+    if args.iter().any(|a| a == "--bill") {
+        let cost = cost.unwrap_or(Box::new(Triangular));
+        let (targets, best_fuel) = optimal_positions(cost.as_ref(), &positions);
+        println!("optimal positions {:?}", targets);
+        for (crab, fuel) in fuel_bill(cost.as_ref(), &positions, targets[0]).iter().enumerate() {
+            println!("crab {} at {}: {}", crab + 1, positions[crab], fuel);
         }
+        println!("{}", best_fuel);
+        return;
     }
 
+    if let Some(cost) = cost {
+        println!("{}", minimize_fuel(cost.as_ref(), &positions).1);
+        return;
+    }
+
+    let (_, best_price) = find_best_position(&positions);
+
     println!("{}", best_price);
 }
 
@@ -53,8 +451,154 @@ mod tests {
     fn test_get_fuel_required_for_position() {
         let submarines = vec![4,3,4,5,2,1,1,5,5];
         let position = 2;
-        let expected_result : usize = 16;
+        let expected_result : usize = 27;
         let actual_result = get_fuel_required_for_position(&submarines, position);
         assert_eq!(actual_result, expected_result);
     }
+
+#[test]
+    fn test_find_best_position_upstream_example() {
+        let submarines = vec![16,1,2,0,4,2,7,1,2,14];
+        assert_eq!(find_best_position(&submarines), (5, 168));
+        assert_eq!(find_best_position(&submarines).1, find_best_position_brute_force(&submarines).1);
+    }
+
+#[test]
+    fn test_find_best_position_matches_brute_force() {
+        let mut seed : u32 = 7;
+        let mut random = |limit: u32| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            ((seed >> 16) % limit) as usize
+        };
+
+        for _ in 0..300 {
+            let count = random(12) + 1;
+            let submarines : Vec<usize> = (0..count).map(|_| random(50)).collect();
+            assert_eq!(find_best_position(&submarines).1, find_best_position_brute_force(&submarines).1, "{:?}", submarines);
+        }
+    }
+
+#[test]
+    fn test_find_best_position_huge_range() {
+        let submarines = vec![0, 1000000000, 3, 999999999, 500000000];
+        assert_eq!(find_best_position(&submarines).0, 500000000);
+    }
+
+#[test]
+    fn test_minimize_fuel_matches_exhaustive_search() {
+        struct Exhaustive<'a>(&'a dyn FuelCost);
+        impl FuelCost for Exhaustive<'_> {
+            fn fuel(&self, crab: usize, distance: usize) -> usize {
+                self.0.fuel(crab, distance)
+            }
+        }
+
+        let submarines = vec![16,1,2,0,4,2,7,1,2,14];
+        assert_eq!(minimize_fuel(&Linear, &submarines), (2, 37));
+        assert_eq!(minimize_fuel(&Triangular, &submarines), (5, 168));
+
+        let mut seed : u32 = 11;
+        let mut random = |limit: u32| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            ((seed >> 16) % limit) as usize
+        };
+
+        let costs : Vec<Box<dyn FuelCost>> = vec![Box::new(Linear), Box::new(Triangular), Box::new(Quadratic)];
+        for _ in 0..100 {
+            let count = random(10) + 1;
+            let submarines : Vec<usize> = (0..count).map(|_| random(40)).collect();
+            for cost in &costs {
+                assert_eq!(minimize_fuel(cost.as_ref(), &submarines), minimize_fuel(&Exhaustive(cost.as_ref()), &submarines), "{:?}", submarines);
+            }
+        }
+    }
+
+#[test]
+    fn test_cost_table() {
+        let table = CostTable::parse("0,1,2,3,4\n0,5,10\n").unwrap();
+        assert!(table.is_convex());
+        assert_eq!(table.fuel(1, 3), usize::MAX);
+        assert_eq!(minimize_fuel(&table, &[0, 4]), (4, 4));
+
+        // cheap to move exactly two steps, not convex
+        let table = CostTable::new(vec![vec![0, 9, 1, 9], vec![0, 9, 1, 9]]);
+        assert!(! table.is_convex());
+        assert_eq!(minimize_fuel(&table, &[0, 3]), (0, 9));
+        assert_eq!(minimize_fuel(&table, &[0, 4]), (2, 2));
+
+        // the search has to stay where every crab can reach, the totals are usize::MAX elsewhere
+        let table = CostTable::new(vec![vec![0, 1, 2], (0..11).collect()]);
+        assert!(table.is_convex());
+        assert_eq!(minimize_fuel(&table, &[10, 0]), (8, 10));
+        assert_eq!(optimal_positions(&table, &[10, 0]), (vec![8, 9, 10], 10));
+        let table = CostTable::new(vec![vec![0, 1], vec![0, 1]]);
+        assert_eq!(minimize_fuel(&table, &[0, 5]), (0, usize::MAX));
+
+        assert!(! CostTable::new(vec![vec![]]).is_convex());
+        assert!(CostTable::parse("1,x").is_err());
+    }
+
+#[test]
+    fn test_optimal_positions_and_bill() {
+        let submarines = vec![1, 5];
+        assert_eq!(optimal_positions(&Linear, &submarines), ((1..=5).collect(), 4));
+        assert_eq!(optimal_positions(&Triangular, &submarines), (vec![3], 6));
+
+        let table = CostTable::new(vec![vec![0, 9, 1, 9, 9], vec![0, 9, 1, 9, 9]]);
+        assert_eq!(optimal_positions(&table, &[0, 4]), (vec![2], 2));
+        let table = CostTable::new(vec![vec![0, 1, 0], vec![0, 1, 0]]);
+        assert_eq!(optimal_positions(&table, &[0, 2]), (vec![0, 2], 0));
+
+        let submarines = vec![16,1,2,0,4,2,7,1,2,14];
+        let bill = fuel_bill(&Linear, &submarines, 2);
+        assert_eq!(bill, vec![14, 1, 0, 2, 2, 0, 5, 1, 0, 12]);
+        assert_eq!(bill.iter().sum::<usize>(), 37);
+    }
+
+#[test]
+    fn test_align_groups() {
+        let submarines = vec![16,1,2,0,4,2,7,1,2,14];
+
+        let alignment = align_groups(&Linear, &submarines, 1).unwrap();
+        assert_eq!(alignment.fuel, 37);
+
+        let alignment = align_groups(&Linear, &submarines, 2).unwrap();
+        assert_eq!(alignment.targets, vec![2, 14]);
+        assert_eq!(alignment.groups, vec![1, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(alignment.fuel, 11 + 2);
+
+        let alignment = align_groups(&Triangular, &submarines, 10).unwrap();
+        assert_eq!(alignment.fuel, 0);
+
+        assert!(align_groups(&Linear, &submarines, 11).is_err());
+        let table = CostTable::new(vec![(0..100).collect(); MAX_TABLE_GROUP_CRABS + 1]);
+        assert!(align_groups(&table, &vec![0; MAX_TABLE_GROUP_CRABS + 1], 2).is_err());
+        assert!(align_groups(&CostTable::new(vec![vec![0, 9, 1]; 10]), &submarines, 2).is_err());
+    }
+
+#[test]
+    fn test_align_groups_prefix_sums_match_cost_tables() {
+        let mut seed : u32 = 5;
+        let mut random = |limit: u32| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            ((seed >> 16) % limit) as usize
+        };
+
+        let costs : Vec<Box<dyn FuelCost>> = vec![Box::new(Linear), Box::new(Triangular), Box::new(Quadratic)];
+        for _ in 0..30 {
+            let count = random(12) + 1;
+            let submarines : Vec<usize> = (0..count).map(|_| random(30)).collect();
+            let group_count = random(count as u32) + 1;
+            for cost in &costs {
+                // the same costs written out as tables take the general path
+                let table = CostTable::new(vec![(0..30).map(|d| cost.fuel(0, d)).collect(); count]);
+                assert!(table.polynomial().is_none());
+                let expected = align_groups(&table, &submarines, group_count).unwrap();
+                let alignment = align_groups(cost.as_ref(), &submarines, group_count).unwrap();
+                assert_eq!(alignment.fuel, expected.fuel, "{:?} into {}", submarines, group_count);
+                let spent : usize = submarines.iter().zip(&alignment.groups).map(|(p, g)| cost.fuel(0, p.abs_diff(alignment.targets[*g]))).sum();
+                assert_eq!(spent, alignment.fuel);
+            }
+        }
+    }
 }
@@ -1,143 +1,611 @@
+use std::env;
 use std::fs;
+use std::collections::BTreeMap;
 use std::collections::HashSet;
 use std::collections::HashMap;
 
 type Digit = HashSet<char>;
 type InputLine = (Vec<Digit>, Vec<Digit>);
-type DetectionRules = Vec<(usize, usize, usize)>;
 
-fn parse_input(input: &str) -> Vec<InputLine> {
-    let mut result : Vec<InputLine> = Vec::new();
+// how to pick out one more pattern of the alphabet: by its segment count alone,
+// or by its segment count together with its overlap with an already known pattern
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Detection {
+    Length { length: usize, unknown: usize },
+    Intersect { known: usize, length: usize, count: usize, unknown: usize },
+}
+
+type DetectionRules = Vec<Detection>;
+
+const STANDARD_DIGITS : &str = "\
+0 abcefg
+1 cf
+2 acdeg
+3 acdfg
+4 bcdf
+5 abdfg
+6 abdefg
+7 acf
+8 abcdefg
+9 abcdfg
+";
+
+struct Alphabet {
+    symbols: Vec<char>,
+    patterns: Vec<Digit>,
+}
+
+impl Alphabet {
+    // one symbol per line, followed by the segments lit to display it
+    fn parse(text: &str) -> Result<Alphabet, String> {
+        let mut symbols : Vec<char> = Vec::new();
+        let mut patterns : Vec<Digit> = Vec::new();
+
+        for (i, line) in text.split('\n').enumerate().filter(|(_, l)| ! l.trim().is_empty()) {
+            let mut fields = line.split_whitespace();
+            let symbol = fields.next().unwrap();
+            let segments = fields.next().ok_or(format!("line {} '{}' has no segments", i + 1, line))?;
+            if fields.next().is_some() || symbol.chars().count() != 1 {
+                return Err(format!("line {} '{}' is not a symbol followed by its segments", i + 1, line));
+            }
+            let symbol = symbol.chars().next().unwrap();
+            let pattern : Digit = segments.chars().collect();
+            if pattern.len() != segments.chars().count() {
+                return Err(format!("line {} '{}' repeats a segment", i + 1, line));
+            }
+            if symbols.contains(&symbol) {
+                return Err(format!("symbol '{}' is defined twice", symbol));
+            }
+            if let Some(other) = patterns.iter().position(|p| *p == pattern) {
+                return Err(format!("symbols '{}' and '{}' light the same segments", symbols[other], symbol));
+            }
+            symbols.push(symbol);
+            patterns.push(pattern);
+        }
 
-    for line in input.trim().split("\n") {
-        let mut line_split = line.split(" | ");
-        let left : &str = line_split.next().expect("failed to read left");
-        let right : &str = line_split.next().expect("failed to read right");
+        if symbols.len() < 2 {
+            return Err("alphabet needs at least two symbols".to_string());
+        }
 
-        let mut left_vec : Vec<HashSet<char>> = Vec::new();
-        left_vec.extend(left.trim().split(" ").map(|s| s.chars().collect()));
-        let mut right_vec : Vec<HashSet<char>> = Vec::new();
-        right_vec.extend(right.trim().split(" ").map(|s| s.chars().collect()));
+        Ok(Alphabet{symbols, patterns})
+    }
 
-        result.push((left_vec, right_vec));
+    fn standard() -> Alphabet {
+        Alphabet::parse(STANDARD_DIGITS).unwrap()
     }
 
-    result
+    fn len(&self) -> usize {
+        self.patterns.len()
+    }
 }
 
-fn generate_intersect_detection() -> DetectionRules {
+fn parse_digits(text: &str) -> Result<Vec<Digit>, String> {
+    let mut digits : Vec<Digit> = Vec::new();
+    for pattern in text.split_whitespace() {
+        let digit : Digit = pattern.chars().collect();
+        if digit.len() != pattern.chars().count() {
+            return Err(format!("pattern '{}' repeats a wire", pattern));
+        }
+        digits.push(digit);
+    }
+    Ok(digits)
+}
+
+fn parse_line(line: &str) -> Result<InputLine, String> {
+    let (left, right) = line.split_once(" | ").ok_or(format!("'{}' has no ' | ' separator", line))?;
+    Ok((parse_digits(left)?, parse_digits(right)?))
+}
+
+fn parse_input(input: &str) -> Vec<Result<InputLine, String>> {
+    input.trim().split('\n').map(parse_line).collect()
+}
+
+fn pattern_string(digit: &Digit) -> String {
+    let mut wires : Vec<char> = digit.iter().copied().collect();
+    wires.sort();
+    wires.into_iter().collect()
+}
+
+fn generate_intersect_detection(alphabet: &Alphabet) -> Result<DetectionRules, String> {
     let mut detections : DetectionRules = Vec::new();
-    let mut unknown_numbers : HashSet<usize> = HashSet::from([0, 2, 3, 5, 6, 9]);
-    let mut known_numbers : HashSet<usize> = HashSet::from([1, 4, 7, 8]);
-    let number_sets : Vec<HashSet<char>> = vec![
-        HashSet::from(['a', 'b', 'c', 'e', 'f', 'g']),
-        HashSet::from(['c', 'f']),
-        HashSet::from(['a', 'c', 'd', 'e', 'g']),
-        HashSet::from(['a', 'c', 'd', 'f', 'g']),
-        HashSet::from(['b', 'c', 'd', 'f']),
-        HashSet::from(['a', 'b', 'd', 'f', 'g']),
-        HashSet::from(['a', 'b', 'd', 'e', 'f', 'g']),
-        HashSet::from(['a', 'c', 'f']),
-        HashSet::from(['a', 'b', 'c', 'd', 'e', 'f', 'g']),
-        HashSet::from(['a', 'b', 'c', 'd', 'f', 'g']),
-    ];
+    let mut unknown_numbers : Vec<usize> = (0..alphabet.len()).collect();
+    let mut known_numbers : Vec<usize> = Vec::new();
+    let patterns = &alphabet.patterns;
 
-    loop {
+    while ! unknown_numbers.is_empty() {
         let mut new_detections : DetectionRules = Vec::new();
+
+        let mut lengths : HashMap<usize, Vec<usize>> = HashMap::new();
+        for unknown_number in &unknown_numbers {
+            lengths.entry(patterns[*unknown_number].len()).or_default().push(*unknown_number);
+        }
+        for (length, numbers) in lengths.iter() {
+            if numbers.len() == 1 {
+                new_detections.push(Detection::Length{length: *length, unknown: numbers[0]});
+            }
+        }
+
         for known_number in &known_numbers {
-            let mut intersections : HashMap<usize, Vec<usize>> = HashMap::new();
+            let mut intersections : HashMap<(usize, usize), Vec<usize>> = HashMap::new();
             for unknown_number in &unknown_numbers {
-                if known_number == unknown_number {
-                    continue;
-                }
-                let intersection = number_sets[*unknown_number].intersection(&number_sets[*known_number]);
-                let intersect_count = intersection.collect::<HashSet<&char>>().len();
-                intersections.entry(intersect_count).or_insert(Vec::new()).push(*unknown_number);
+                let pattern = &patterns[*unknown_number];
+                let intersect_count = pattern.intersection(&patterns[*known_number]).count();
+                intersections.entry((pattern.len(), intersect_count)).or_default().push(*unknown_number);
             }
-            for (count, numbers) in intersections.iter() {
+            for ((length, count), numbers) in intersections.iter() {
                 if numbers.len() == 1 {
-                    new_detections.push((*known_number, *count, numbers[0]))
+                    new_detections.push(Detection::Intersect{known: *known_number, length: *length, count: *count, unknown: numbers[0]});
                 }
             }
+        }
 
+        if new_detections.is_empty() {
+            let mut symbols : Vec<char> = unknown_numbers.iter().map(|n| alphabet.symbols[*n]).collect();
+            symbols.sort();
+            let symbols : String = symbols.iter().collect();
+            return Err(format!("alphabet is ambiguous, cannot tell apart symbols '{}'", symbols));
         }
-        for (known_number, count, unknown_number) in &new_detections {
-                if known_numbers.contains(unknown_number) {
-                    continue;
-                }
-                known_numbers.insert(*unknown_number);
-                unknown_numbers.remove(unknown_number);
-                detections.push((*known_number, *count, *unknown_number));
+
+        // keep the rules in a stable order so the decoder behaves the same on every run
+        new_detections.sort_by_key(|detection| match *detection {
+            Detection::Length{unknown, ..} => (unknown, 0),
+            Detection::Intersect{known, unknown, ..} => (unknown, known + 1),
+        });
+        for detection in new_detections {
+            let (Detection::Length{unknown, ..} | Detection::Intersect{unknown, ..}) = detection;
+            if known_numbers.contains(&unknown) {
+                continue;
+            }
+            known_numbers.push(unknown);
+            unknown_numbers.retain(|n| *n != unknown);
+            detections.push(detection);
         }
-        if new_detections.len() == 0 {
-            break;
+    }
+
+    Ok(detections)
+}
+
+fn rule_matches(rule: &Detection, known_digits: &[Digit], digit: &Digit) -> bool {
+    match *rule {
+        Detection::Length{length, ..} => digit.len() == length,
+        Detection::Intersect{known, length, count, ..} =>
+            digit.len() == length && known_digits[known].intersection(digit).count() == count,
+    }
+}
+
+#[derive(Debug)]
+struct Decoding {
+    wiring: BTreeMap<char, char>,
+    output: Vec<usize>,
+}
+
+impl Decoding {
+    fn value(&self, base: usize) -> usize {
+        self.output.iter().fold(0, |value, digit| value * base + digit)
+    }
+}
+
+// a wire drives the segment that is lit by exactly the same symbols
+fn derive_wiring(known_digits: &[Digit], alphabet: &Alphabet) -> Result<BTreeMap<char, char>, String> {
+    let lit_by = |patterns: &[Digit], c: char| -> Vec<usize> {
+        (0..patterns.len()).filter(|n| patterns[*n].contains(&c)).collect()
+    };
+
+    let mut segments : Vec<char> = alphabet.patterns.iter().flatten().copied().collect::<HashSet<char>>().into_iter().collect();
+    segments.sort();
+    let mut wires : Vec<char> = known_digits.iter().flatten().copied().collect::<HashSet<char>>().into_iter().collect();
+    wires.sort();
+
+    let mut wiring : BTreeMap<char, char> = BTreeMap::new();
+    for wire in wires {
+        let symbols = lit_by(known_digits, wire);
+        let candidates : Vec<char> = segments.iter().copied().filter(|s| lit_by(&alphabet.patterns, *s) == symbols).collect();
+        match candidates.len() {
+            0 => return Err(format!("wire '{}' does not match any segment", wire)),
+            1 => {},
+            _ => return Err(format!("wire '{}' could drive any of segments '{}'", wire, candidates.iter().collect::<String>())),
         }
+        if let Some((other, _)) = wiring.iter().find(|(_, segment)| **segment == candidates[0]) {
+            return Err(format!("wires '{}' and '{}' both drive segment '{}'", other, wire, candidates[0]));
+        }
+        wiring.insert(wire, candidates[0]);
     }
-    assert_eq!(known_numbers.len(), 10);
 
-    detections
+    Ok(wiring)
 }
 
-fn get_number_from_line(line: &InputLine, rules: &DetectionRules) -> usize {
+fn decode_line(line: &InputLine, alphabet: &Alphabet, rules: &DetectionRules) -> Result<Decoding, String> {
+    let lengths : HashSet<usize> = alphabet.patterns.iter().map(|p| p.len()).collect();
+    for (i, digit) in line.0.iter().enumerate() {
+        if ! lengths.contains(&digit.len()) {
+            return Err(format!("pattern '{}' has {} segments, no symbol has", pattern_string(digit), digit.len()));
+        }
+        if line.0[..i].contains(digit) {
+            return Err(format!("pattern '{}' appears twice", pattern_string(digit)));
+        }
+    }
+
     let mut unknown_digits = line.0.clone();
-    let mut known_digits : Vec<Digit> = vec![Digit::new(); 10];
-
-    // first fill the digits we can recognize by unique length
-    let unique_lengths = [(2, 1), (4, 4), (3, 7), (7, 8)];
-    for digit in & mut unknown_digits {
-        for (len, value) in unique_lengths {
-            if digit.len() == len {
-                known_digits[value].extend(digit.drain());
-                break;
-            }
+    let mut known_digits : Vec<Digit> = vec![Digit::new(); alphabet.len()];
+
+    // identified patterns are drained, so they never match a rule again
+    for rule in rules {
+        let (Detection::Length{unknown, ..} | Detection::Intersect{unknown, ..}) = *rule;
+        match unknown_digits.iter_mut().find(|digit| rule_matches(rule, &known_digits, digit)) {
+            Some(digit) => known_digits[unknown].extend(digit.drain()),
+            None => return Err(format!("no pattern for symbol '{}'", alphabet.symbols[unknown])),
         }
     }
-    for (len, value) in unique_lengths {
-        assert_eq!(known_digits[value].len(), len);
+    if let Some(digit) = unknown_digits.iter().find(|digit| ! digit.is_empty()) {
+        return Err(format!("pattern '{}' is not any symbol", pattern_string(digit)));
     }
 
-    // now use detection rules to recognize other digits by intersection lengths
-    for (known, count, unknown) in rules {
-        for digit in & mut unknown_digits {
-            if known_digits[*known].intersection(&digit).collect::<HashSet<&char>>().len() == *count {
-                known_digits[*unknown].extend(digit.drain());
-                break;
-            }
+    let wiring = derive_wiring(&known_digits, alphabet)?;
+    for (digit, pattern) in known_digits.iter().zip(&alphabet.patterns) {
+        let wired : Digit = digit.iter().map(|wire| wiring[wire]).collect();
+        if wired != *pattern {
+            return Err(format!("pattern '{}' does not fit the wiring", pattern_string(digit)));
         }
-        assert_ne!(known_digits[*unknown].len(), 0);
     }
-    for digit in &known_digits {
-        assert_ne!(digit.len(), 0);
+
+    // now we know all the digits, let's decode the output
+    let mut output : Vec<usize> = Vec::new();
+    for digit in &line.1 {
+        match known_digits.iter().position(|known| known == digit) {
+            Some(value) => output.push(value),
+            None => return Err(format!("output digit '{}' is not among the patterns", pattern_string(digit))),
+        }
     }
 
-    // now we know all the digits, let's decode input number and start filling result
-    let mut result: usize = 0;
+    Ok(Decoding{wiring, output})
+}
 
-    for digit in &line.1 {
-        result *= 10;
-        for i in 0..known_digits.len() {
-            if *digit == known_digits[i] {
-                result += i;
-                break;
+// stop enumerating wirings once this many fit, the line says too little to decode
+const WIRING_LIMIT : usize = 10000;
+
+// symbols a pattern could be showing, judging by its segment count alone
+fn pattern_candidates(digit: &Digit, alphabet: &Alphabet) -> Vec<usize> {
+    (0..alphabet.len()).filter(|n| alphabet.patterns[*n].len() == digit.len()).collect()
+}
+
+// narrow down the segments each wire could drive, from the segment counts of the
+// patterns and then by taking the wires that are already pinned down out of the others
+fn wire_domains(patterns: &[(Digit, Vec<usize>)], alphabet: &Alphabet, segments: &[char]) -> Result<BTreeMap<char, HashSet<char>>, String> {
+    let all_segments : HashSet<char> = segments.iter().copied().collect();
+    let mut domains : BTreeMap<char, HashSet<char>> = segments.iter().map(|wire| (*wire, all_segments.clone())).collect();
+
+    for (digit, candidates) in patterns {
+        let lit : HashSet<char> = candidates.iter().flat_map(|n| alphabet.patterns[*n].iter().copied()).collect();
+        let dark : HashSet<char> = candidates.iter().flat_map(|n| all_segments.difference(&alphabet.patterns[*n]).copied()).collect();
+        for (wire, domain) in domains.iter_mut() {
+            let allowed = if digit.contains(wire) { &lit } else { &dark };
+            domain.retain(|segment| allowed.contains(segment));
+        }
+    }
+
+    loop {
+        if let Some((wire, _)) = domains.iter().find(|(_, domain)| domain.is_empty()) {
+            return Err(format!("wire '{}' cannot drive any segment", wire));
+        }
+        let pinned : Vec<(char, char)> = domains.iter()
+            .filter(|(_, domain)| domain.len() == 1)
+            .map(|(wire, domain)| (*wire, *domain.iter().next().unwrap()))
+            .collect();
+        let mut changed = false;
+        for (wire, domain) in domains.iter_mut() {
+            for (pinned_wire, segment) in &pinned {
+                if wire != pinned_wire && domain.remove(segment) {
+                    changed = true;
+                }
             }
         }
+        if ! changed {
+            return Ok(domains);
+        }
+    }
+}
+
+// whether some candidate symbol still agrees with the wires assigned so far
+fn pattern_fits(digit: &Digit, candidates: &[usize], alphabet: &Alphabet, assignment: &BTreeMap<char, char>) -> bool {
+    candidates.iter().any(|n| {
+        let pattern = &alphabet.patterns[*n];
+        assignment.iter().all(|(wire, segment)| digit.contains(wire) == pattern.contains(segment))
+    })
+}
+
+fn search_wirings(
+    order: &[char],
+    domains: &BTreeMap<char, HashSet<char>>,
+    patterns: &[(Digit, Vec<usize>)],
+    alphabet: &Alphabet,
+    assignment: &mut BTreeMap<char, char>,
+    wirings: &mut Vec<BTreeMap<char, char>>,
+) {
+    if wirings.len() >= WIRING_LIMIT {
+        return;
+    }
+    let Some((wire, rest)) = order.split_first() else {
+        wirings.push(assignment.clone());
+        return;
+    };
+
+    let mut choices : Vec<char> = domains[wire].iter().copied().collect();
+    choices.sort();
+    for segment in choices {
+        if assignment.values().any(|s| *s == segment) {
+            continue;
+        }
+        assignment.insert(*wire, segment);
+        if patterns.iter().all(|(digit, candidates)| pattern_fits(digit, candidates, alphabet, assignment)) {
+            search_wirings(rest, domains, patterns, alphabet, assignment, wirings);
+        }
+        assignment.remove(wire);
+    }
+}
+
+// treat the wiring as a constraint satisfaction problem, which works with any
+// number of sample patterns as long as the output comes out the same in every
+// wiring that fits; wires that differ between those wirings are left out
+fn solve_line(line: &InputLine, alphabet: &Alphabet) -> Result<Decoding, String> {
+    let mut segments : Vec<char> = alphabet.patterns.iter().flatten().copied().collect::<HashSet<char>>().into_iter().collect();
+    segments.sort();
+
+    let mut patterns : Vec<(Digit, Vec<usize>)> = Vec::new();
+    for digit in line.0.iter().chain(&line.1) {
+        if let Some(wire) = digit.iter().find(|wire| ! segments.contains(wire)) {
+            return Err(format!("pattern '{}' uses unknown wire '{}'", pattern_string(digit), wire));
+        }
+        let candidates = pattern_candidates(digit, alphabet);
+        if candidates.is_empty() {
+            return Err(format!("pattern '{}' has {} segments, no symbol has", pattern_string(digit), digit.len()));
+        }
+        patterns.push((digit.clone(), candidates));
     }
+    if let Some((_, digit)) = line.0.iter().enumerate().find(|(i, digit)| line.0[..*i].contains(digit)) {
+        return Err(format!("pattern '{}' appears twice", pattern_string(digit)));
+    }
+
+    let domains = wire_domains(&patterns, alphabet, &segments)?;
+    let mut order : Vec<char> = segments.clone();
+    order.sort_by_key(|wire| domains[wire].len());
 
-    result
+    let mut wirings : Vec<BTreeMap<char, char>> = Vec::new();
+    search_wirings(&order, &domains, &patterns, alphabet, &mut BTreeMap::new(), &mut wirings);
+    if wirings.is_empty() {
+        return Err("no wiring fits the patterns".to_string());
+    }
+    if wirings.len() >= WIRING_LIMIT {
+        return Err(format!("more than {} wirings fit the patterns", WIRING_LIMIT));
+    }
+
+    let decode = |wiring: &BTreeMap<char, char>| -> Vec<usize> {
+        line.1.iter().map(|digit| {
+            let wired : Digit = digit.iter().map(|wire| wiring[wire]).collect();
+            alphabet.patterns.iter().position(|pattern| *pattern == wired).unwrap()
+        }).collect()
+    };
+    let output = decode(&wirings[0]);
+    if wirings.iter().any(|wiring| decode(wiring) != output) {
+        return Err(format!("{} wirings fit the patterns and disagree on the output", wirings.len()));
+    }
+
+    let mut wiring = wirings[0].clone();
+    wiring.retain(|wire, segment| wirings.iter().all(|other| other[wire] == *segment));
+
+    Ok(Decoding{wiring, output})
+}
+
+fn describe_rule(rule: &Detection, alphabet: &Alphabet) -> String {
+    match *rule {
+        Detection::Length{length, unknown} =>
+            format!("{} is the only one with {} segments", alphabet.symbols[unknown], length),
+        Detection::Intersect{known, length, count, unknown} =>
+            format!("{} has {} intersections only with {} among {} segments", alphabet.symbols[known], count, alphabet.symbols[unknown], length),
+    }
+}
+
+fn get_option<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    let prefix = format!("--{}=", name);
+    args.iter().find_map(|a| a.strip_prefix(&prefix))
 }
 
 fn main() {
     let input_string = fs::read_to_string("input.txt").expect("failed to read input string");
 
-    let detection_rules = generate_intersect_detection();
-    for (known_number, count, unknown_number) in &detection_rules {
-        println!("{} has {} intersections only with {}", known_number, count, unknown_number);
+    let args : Vec<String> = env::args().skip(1).collect();
+    let alphabet = match get_option(&args, "alphabet") {
+        Some(path) => {
+            let text = fs::read_to_string(path).expect("failed to read alphabet");
+            Alphabet::parse(&text).expect("invalid alphabet")
+        },
+        None => Alphabet::standard(),
+    };
+
+    let detection_rules = match get_option(&args, "strategy").unwrap_or("rules") {
+        "rules" => match generate_intersect_detection(&alphabet) {
+            Ok(rules) => Some(rules),
+            Err(message) => panic!("{}", message),
+        },
+        "constraints" => None,
+        other => panic!("unknown strategy '{}', expected rules or constraints", other),
+    };
+    for rule in detection_rules.iter().flatten() {
+        println!("{}", describe_rule(rule, &alphabet));
     }
+    let decode = |line: &InputLine| match &detection_rules {
+        Some(rules) => decode_line(line, &alphabet, rules),
+        None => solve_line(line, &alphabet),
+    };
 
-    let input_data = parse_input(&input_string);
+    let show_wiring = args.iter().any(|a| a == "--wiring");
     let mut result : usize = 0;
-    for input_line in input_data {
-        result += get_number_from_line(&input_line, &detection_rules);
+    let mut failures : usize = 0;
+    for (i, input_line) in parse_input(&input_string).into_iter().enumerate() {
+        match input_line.and_then(|line| decode(&line)) {
+            Ok(decoding) => {
+                if show_wiring {
+                    let wiring : Vec<String> = decoding.wiring.iter().map(|(wire, segment)| format!("{}->{}", wire, segment)).collect();
+                    println!("line {}: {}", i + 1, wiring.join(" "));
+                }
+                result += decoding.value(alphabet.len());
+            },
+            Err(message) => {
+                eprintln!("line {}: {}", i + 1, message);
+                failures += 1;
+            },
+        }
+    }
+    if failures > 0 {
+        eprintln!("skipped {} lines that could not be decoded", failures);
     }
     println!("{}", result);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE : &str = "\
+be cfbegad cbdgef fgaecd cgeb fdcge agebfd fecdb fabcd edb | fdgacbe cefdb cefbgd gcbe
+edbfga begcd cbg gc gcadebf fbgde acbgfd abcde gfcbed gfec | fcgedb cgb dgebacf gc
+fgaebd cg bdaec gdafb agbcfd gdcbef bgcad gfac gcb cdgabef | cg cg fdcagb cbg
+";
+
+    // hexadecimal digits, with the lowercase b and d to keep them apart from 8 and 0
+    const HEX_DIGITS : &str = "\
+0 abcefg
+1 cf
+2 acdeg
+3 acdfg
+4 bcdf
+5 abdfg
+6 abdefg
+7 acf
+8 abcdefg
+9 abcdfg
+A abcdef
+b bdefg
+C abeg
+d cdefg
+E abdeg
+F abde
+";
+
+    // scramble the wires of the alphabet patterns into an input line
+    fn scrambled_line(alphabet: &Alphabet, wires: &str, output: &[usize]) -> InputLine {
+        let segments : Vec<char> = "abcdefg".chars().collect();
+        let wires : Vec<char> = wires.chars().collect();
+        let scramble = |pattern: &Digit| -> Digit { pattern.iter().map(|c| wires[segments.iter().position(|s| s == c).unwrap()]).collect() };
+        let patterns = alphabet.patterns.iter().rev().map(scramble).collect();
+        let output = output.iter().map(|n| scramble(&alphabet.patterns[*n])).collect();
+        (patterns, output)
+    }
+
+#[test]
+    fn test_standard_digits_upstream_example() {
+        let alphabet = Alphabet::standard();
+        let rules = generate_intersect_detection(&alphabet).unwrap();
+        let values : Vec<usize> = parse_input(EXAMPLE).iter().map(|line| decode_line(line.as_ref().unwrap(), &alphabet, &rules).unwrap().value(10)).collect();
+        assert_eq!(values, vec![8394, 9781, 1197]);
+    }
+
+#[test]
+    fn test_hex_digits() {
+        let alphabet = Alphabet::parse(HEX_DIGITS).unwrap();
+        let rules = generate_intersect_detection(&alphabet).unwrap();
+        let line = scrambled_line(&alphabet, "gfedcba", &[0xC, 0x0, 0xF, 0xE]);
+        assert_eq!(decode_line(&line, &alphabet, &rules).unwrap().value(16), 0xC0FE);
+        let line = scrambled_line(&alphabet, "dagbfce", &[0xB, 0xA, 0xD, 0x5]);
+        assert_eq!(decode_line(&line, &alphabet, &rules).unwrap().value(16), 0xBAD5);
+    }
+
+#[test]
+    fn test_ambiguous_alphabets() {
+        // mirror images of each other, no rule can tell which wire is which
+        let message = generate_intersect_detection(&Alphabet::parse("L bef\nJ cfg\n").unwrap()).unwrap_err();
+        assert_eq!(message, "alphabet is ambiguous, cannot tell apart symbols 'JL'");
+
+        assert!(generate_intersect_detection(&Alphabet::parse("1 cf\n4 bcdf\nL bef\nJ cfg\n").unwrap()).is_ok());
+    }
+
+#[test]
+    fn test_parse_alphabet() {
+        assert!(Alphabet::parse("").is_err());
+        assert_eq!(Alphabet::parse("1 cf\n7 fc\n").err().unwrap(), "symbols '1' and '7' light the same segments");
+        assert_eq!(Alphabet::parse("1 cf\n1 acf\n").err().unwrap(), "symbol '1' is defined twice");
+        assert_eq!(Alphabet::parse("1 cf\n7\n").err().unwrap(), "line 2 '7' has no segments");
+        assert!(Alphabet::parse("1 cff\n7 acf\n").is_err());
+        assert!(Alphabet::parse("10 cf\n7 acf\n").is_err());
+    }
+
+#[test]
+    fn test_wiring() {
+        let alphabet = Alphabet::standard();
+        let rules = generate_intersect_detection(&alphabet).unwrap();
+        let line = parse_line("acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf").unwrap();
+        let decoding = decode_line(&line, &alphabet, &rules).unwrap();
+        let wiring : String = decoding.wiring.values().collect();
+        assert_eq!(wiring, "cfgabde");
+        assert_eq!(decoding.output, vec![5, 3, 5, 3]);
+        assert_eq!(decoding.value(10), 5353);
+    }
+
+#[test]
+    fn test_corrupted_lines() {
+        let alphabet = Alphabet::standard();
+        let rules = generate_intersect_detection(&alphabet).unwrap();
+        let decode = |text: &str| parse_line(text).and_then(|line| decode_line(&line, &alphabet, &rules).map(|d| d.value(10)));
+
+        assert_eq!(decode("acedgfb cdfbe gcdfa"), Err("'acedgfb cdfbe gcdfa' has no ' | ' separator".to_string()));
+        assert_eq!(decode("acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb aa | ab"), Err("pattern 'aa' repeats a wire".to_string()));
+        assert_eq!(decode("acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb a | ab"), Err("pattern 'a' has 1 segments, no symbol has".to_string()));
+        assert_eq!(decode("acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb dab | ab"), Err("pattern 'abd' appears twice".to_string()));
+        assert_eq!(decode("acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb ab | ab"), Err("no pattern for symbol '0'".to_string()));
+        assert_eq!(decode("acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | abc"), Err("output digit 'abc' is not among the patterns".to_string()));
+        // two patterns swapped one wire, so the digits no longer share a single wiring
+        assert!(decode("acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagfdb ab | ab").is_err());
+        assert_eq!(decode("acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | ab"), Ok(1));
+    }
+
+#[test]
+    fn test_constraints_agree_with_rules() {
+        let alphabet = Alphabet::standard();
+        let rules = generate_intersect_detection(&alphabet).unwrap();
+        let mut text = EXAMPLE.to_string();
+        text.push_str("acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf\n");
+        for line in parse_input(&text) {
+            let line = line.unwrap();
+            let expected = decode_line(&line, &alphabet, &rules).unwrap();
+            let solved = solve_line(&line, &alphabet).unwrap();
+            assert_eq!(solved.wiring, expected.wiring);
+            assert_eq!(solved.output, expected.output);
+        }
+
+        let alphabet = Alphabet::parse(HEX_DIGITS).unwrap();
+        let line = scrambled_line(&alphabet, "dagbfce", &[0xB, 0xA, 0xD, 0x5]);
+        assert_eq!(solve_line(&line, &alphabet).unwrap().value(16), 0xBAD5);
+    }
+
+#[test]
+    fn test_constraints_with_few_patterns() {
+        let alphabet = Alphabet::standard();
+
+        // 1, 7, 4 and 8 alone pin down a, and pair up the other wires
+        let line = parse_line("ab dab eafb acedgfb | ab dab").unwrap();
+        let decoding = solve_line(&line, &alphabet).unwrap();
+        assert_eq!(decoding.output, vec![1, 7]);
+        assert_eq!(decoding.wiring.iter().map(|(wire, segment)| format!("{}{}", wire, segment)).collect::<Vec<String>>(), vec!["da"]);
+
+        // the output digits take part in the constraints too
+        let line = parse_line("ab dab eafb | cdfbe fcadb").unwrap();
+        assert_eq!(solve_line(&line, &alphabet).unwrap().value(10), 53);
+
+        let line = parse_line("ab dab | cdfbe").unwrap();
+        assert!(solve_line(&line, &alphabet).unwrap_err().ends_with("disagree on the output"));
+
+        // two 3-segment patterns, but 7 is the only symbol with three segments
+        let line = parse_line("ab abc abd | ab").unwrap();
+        assert_eq!(solve_line(&line, &alphabet).unwrap_err(), "wire 'c' cannot drive any segment");
+    }
+
+}
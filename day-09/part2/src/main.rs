@@ -1,5 +1,9 @@
+use std::collections::HashSet;
+use std::env;
 use std::fs;
 
+use ansi_term::{Colour, Style};
+
 type Coords = (usize, usize);
 type InputMapRow = Vec<u32>;
 type InputMap = Vec<InputMapRow>;
@@ -25,57 +29,295 @@ fn make_input_map(input: &str) -> InputMap {
     result
 }
 
-fn get_neighbours(tile: Coords) -> Vec<Coords> {
-    vec![
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Connectivity {
+    Four,
+    Eight,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BasinRules {
+    // cells at least this high separate basins
+    wall: u32,
+    connectivity: Connectivity,
+    // a flat region whose neighbours are all higher counts as one low area
+    plateaus: bool,
+}
+
+const PUZZLE_RULES : BasinRules = BasinRules{wall: 9, connectivity: Connectivity::Four, plateaus: false};
+
+fn parse_connectivity(text: &str) -> Result<Connectivity, String> {
+    match text {
+        "4" => Ok(Connectivity::Four),
+        "8" => Ok(Connectivity::Eight),
+        _ => Err(format!("unknown connectivity '{}', expected 4 or 8", text)),
+    }
+}
+
+fn get_neighbours(tile: Coords, connectivity: Connectivity) -> Vec<Coords> {
+    let mut neighbours = vec![
         (tile.0-1, tile.1),
         (tile.0+1, tile.1),
         (tile.0, tile.1-1),
         (tile.0, tile.1+1)
-    ]
+    ];
+    if connectivity == Connectivity::Eight {
+        neighbours.extend([
+            (tile.0-1, tile.1-1),
+            (tile.0-1, tile.1+1),
+            (tile.0+1, tile.1-1),
+            (tile.0+1, tile.1+1)
+        ]);
+    }
+    neighbours
+}
+
+fn is_inside(map: &InputMap, tile: Coords) -> bool {
+    tile.0 > 0 && tile.0 < map.len()-1 && tile.1 > 0 && tile.1 < map[0].len()-1
+}
+
+// the padding around the map is always a wall, whatever the threshold
+fn is_wall(map: &InputMap, tile: Coords, rules: &BasinRules) -> bool {
+    ! is_inside(map, tile) || map[tile.0][tile.1] >= rules.wall
 }
 
-fn find_low_spots(map: &InputMap) -> Vec<(usize, usize)> {
-    let mut low_spots = Vec::<(usize, usize)>::new();
+// regions of equal height with only higher neighbours; without plateaus
+// only single cells qualify
+fn find_low_areas(map: &InputMap, rules: &BasinRules) -> Vec<Vec<Coords>> {
+    let mut seen : Vec<Vec<bool>> = vec![vec![false; map[0].len()]; map.len()];
+    let mut low_areas : Vec<Vec<Coords>> = Vec::new();
+
     for i in 1..map.len()-1 {
-        'point: for j in 1..map[i].len()-1 {
-            for n in get_neighbours((i, j)) {
-                if map[n.0][n.1] <= map[i][j] {
-                    continue 'point;
+        for j in 1..map[i].len()-1 {
+            if seen[i][j] || is_wall(map, (i, j), rules) {
+                continue;
+            }
+
+            let height = map[i][j];
+            let mut area : Vec<Coords> = Vec::new();
+            let mut is_low = true;
+            let mut stack : Vec<Coords> = vec![(i, j)];
+            seen[i][j] = true;
+            while let Some(tile) = stack.pop() {
+                area.push(tile);
+                for n in get_neighbours(tile, rules.connectivity) {
+                    if map[n.0][n.1] < height {
+                        is_low = false;
+                    } else if map[n.0][n.1] == height {
+                        if ! is_inside(map, n) {
+                            is_low = false;
+                        } else if ! seen[n.0][n.1] {
+                            seen[n.0][n.1] = true;
+                            stack.push(n);
+                        }
+                    }
                 }
             }
-            low_spots.push((i, j));
+
+            if is_low && (rules.plateaus || area.len() == 1) {
+                area.sort_unstable();
+                low_areas.push(area);
+            }
         }
     }
-    low_spots
+
+    low_areas.sort_unstable();
+    low_areas
+}
+
+fn find_low_spots(map: &InputMap, rules: &BasinRules) -> Vec<(usize, usize)> {
+    find_low_areas(map, rules).iter().map(|area| area[0]).collect()
+}
+
+#[derive(Debug, PartialEq)]
+struct Basin {
+    low_point: Coords,
+    cells: Vec<Coords>,
+}
+
+impl Basin {
+    fn size(&self) -> usize {
+        self.cells.len()
+    }
 }
 
-fn measure_basin_impl(map: & mut InputMap, pos: Coords) -> u32 {
-    let mut result : u32 = 0;
-    if map[pos.0][pos.1] != 9 {
-        map[pos.0][pos.1] = 9;
-        result = 1;
-        for n in get_neighbours(pos) {
-            result += measure_basin_impl(map, n);
+struct BasinMap {
+    labels: Vec<Vec<Option<usize>>>,
+    basins: Vec<Basin>,
+}
+
+impl BasinMap {
+    fn basin_at(&self, tile: Coords) -> Option<&Basin> {
+        self.labels[tile.0][tile.1].map(|label| &self.basins[label])
+    }
+
+    fn largest_labels(&self, count: usize) -> Vec<usize> {
+        let mut labels : Vec<usize> = (0..self.basins.len()).collect();
+        labels.sort_by_key(|label| std::cmp::Reverse(self.basins[*label].size()));
+        labels.truncate(count);
+        labels
+    }
+
+    fn largest_basins(&self, count: usize) -> Vec<&Basin> {
+        self.largest_labels(count).iter().map(|label| &self.basins[*label]).collect()
+    }
+}
+
+// label every cell below the wall in one pass, flooding each new basin with an explicit stack
+fn label_basins(map: &InputMap, rules: &BasinRules) -> BasinMap {
+    let mut labels : Vec<Vec<Option<usize>>> = vec![vec![None; map[0].len()]; map.len()];
+    let mut basins : Vec<Basin> = Vec::new();
+
+    for i in 1..map.len()-1 {
+        for j in 1..map[i].len()-1 {
+            if is_wall(map, (i, j), rules) || labels[i][j].is_some() {
+                continue;
+            }
+
+            let label = basins.len();
+            let mut basin = Basin{low_point: (i, j), cells: Vec::new()};
+            let mut stack : Vec<Coords> = vec![(i, j)];
+            labels[i][j] = Some(label);
+            while let Some(tile) = stack.pop() {
+                basin.cells.push(tile);
+                if map[tile.0][tile.1] < map[basin.low_point.0][basin.low_point.1] {
+                    basin.low_point = tile;
+                }
+                for n in get_neighbours(tile, rules.connectivity) {
+                    if ! is_wall(map, n, rules) && labels[n.0][n.1].is_none() {
+                        labels[n.0][n.1] = Some(label);
+                        stack.push(n);
+                    }
+                }
+            }
+            basin.cells.sort_unstable();
+            basins.push(basin);
         }
     }
-    result
+
+    BasinMap{labels, basins}
 }
 
-fn measure_basin(mut map: InputMap, low_spot: Coords) -> u32 {
-    measure_basin_impl(&mut map, low_spot)
+fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> (u8, u8, u8) {
+    let sector = hue * 6.0;
+    let fraction = sector - sector.floor();
+    let p = value * (1.0 - saturation);
+    let q = value * (1.0 - saturation * fraction);
+    let t = value * (1.0 - saturation * (1.0 - fraction));
+    let (r, g, b) = match sector as u32 % 6 {
+        0 => (value, t, p),
+        1 => (q, value, p),
+        2 => (p, value, t),
+        3 => (p, q, value),
+        4 => (t, p, value),
+        _ => (value, p, q),
+    };
+    ((r * 255.0).round() as u8, (g * 255.0).round() as u8, (b * 255.0).round() as u8)
+}
+
+// step round the colour wheel by the golden ratio so neighbouring labels never look alike;
+// basins that are not highlighted are drawn darker
+fn basin_colour(label: usize, highlighted: bool) -> (u8, u8, u8) {
+    let hue = (label as f64 * 0.618_033_988_75).fract();
+    hsv_to_rgb(hue, 0.65, if highlighted { 1.0 } else { 0.45 })
+}
+
+const WALL_COLOUR : (u8, u8, u8) = (40, 40, 40);
+const LOW_POINT_COLOUR : (u8, u8, u8) = (255, 255, 255);
+
+struct BasinPicture<'a> {
+    map: &'a InputMap,
+    basin_map: &'a BasinMap,
+    low_points: HashSet<Coords>,
+    highlighted: HashSet<usize>,
+}
+
+impl<'a> BasinPicture<'a> {
+    fn new(map: &'a InputMap, basin_map: &'a BasinMap, rules: &BasinRules) -> Self {
+        let low_points = find_low_areas(map, rules).into_iter().flatten().collect();
+        let highlighted = basin_map.largest_labels(3).into_iter().collect();
+        BasinPicture{map, basin_map, low_points, highlighted}
+    }
+
+    fn colour_at(&self, tile: Coords) -> (u8, u8, u8) {
+        match self.basin_map.labels[tile.0][tile.1] {
+            Some(label) => basin_colour(label, self.highlighted.contains(&label)),
+            None => WALL_COLOUR,
+        }
+    }
+
+    // heights as digits, coloured by basin, with the low points reversed
+    fn render_ansi(&self) -> String {
+        let mut result = String::new();
+        for i in 1..self.map.len()-1 {
+            for j in 1..self.map[i].len()-1 {
+                let (r, g, b) = self.colour_at((i, j));
+                let mut style = Style::new().fg(Colour::RGB(r, g, b));
+                if self.low_points.contains(&(i, j)) {
+                    style = style.bold().reverse();
+                }
+                result.push_str(&style.paint(self.map[i][j].to_string()).to_string());
+            }
+            result.push('\n');
+        }
+        result
+    }
+
+    // plain (ASCII) PPM, one pixel per cell
+    fn render_ppm(&self) -> String {
+        let mut result = format!("P3\n{} {}\n255\n", self.map[0].len() - 2, self.map.len() - 2);
+        for i in 1..self.map.len()-1 {
+            let row : Vec<String> = (1..self.map[i].len()-1).map(|j| {
+                let (r, g, b) = if self.low_points.contains(&(i, j)) { LOW_POINT_COLOUR } else { self.colour_at((i, j)) };
+                format!("{} {} {}", r, g, b)
+            }).collect();
+            result.push_str(&row.join(" "));
+            result.push('\n');
+        }
+        result
+    }
+}
+
+fn get_option<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    let prefix = format!("--{}=", name);
+    args.iter().find_map(|a| a.strip_prefix(&prefix))
 }
 
 fn main() {
     let input = fs::read_to_string("input.txt").expect("failed to read input file");
     let input_map = make_input_map(&input);
-    let low_spots = find_low_spots(&input_map);
-    let mut sizes : Vec<u32> = low_spots.iter().map(|t| measure_basin(input_map.clone(), *t)).collect();
-    sizes.sort_unstable();
-    sizes.reverse();
-    let mut result : u32 = 1;
-    for s in sizes[..3].iter() {
-        result *= s;
+    let args : Vec<String> = env::args().skip(1).collect();
+    let mut rules = PUZZLE_RULES;
+    if let Some(wall) = get_option(&args, "wall") {
+        rules.wall = wall.parse().expect("invalid wall height");
     }
+    if let Some(connectivity) = get_option(&args, "connectivity") {
+        rules.connectivity = parse_connectivity(connectivity).expect("invalid connectivity");
+    }
+    if args.iter().any(|a| a == "--plateaus") {
+        rules.plateaus = true;
+    }
+
+    let basin_map = label_basins(&input_map, &rules);
+
+    if args.iter().any(|a| a == "--low-spots") {
+        for spot in find_low_spots(&input_map, &rules) {
+            let basin = basin_map.basin_at(spot).unwrap();
+            println!("{},{} height {} basin of {}", spot.0 - 1, spot.1 - 1, input_map[spot.0][spot.1], basin.size());
+        }
+    }
+
+    if args.iter().any(|a| a == "--ansi") || get_option(&args, "ppm").is_some() {
+        let picture = BasinPicture::new(&input_map, &basin_map, &rules);
+        if args.iter().any(|a| a == "--ansi") {
+            print!("{}", picture.render_ansi());
+        }
+        if let Some(path) = get_option(&args, "ppm") {
+            fs::write(path, picture.render_ppm()).expect("PPM file write failed");
+        }
+    }
+
+    let result : usize = basin_map.largest_basins(3).iter().map(|basin| basin.size()).product();
     println!("{}", result);
 }
 
@@ -112,7 +354,7 @@ mod tests {
         15
     }
 
-    fn basin_size(low_spot: Coords) -> u32 {
+    fn basin_size(low_spot: Coords) -> usize {
         if low_spot == (1, 2) {
             3
         } else if low_spot == (1, 10) {
@@ -133,13 +375,119 @@ mod tests {
 
 #[test]
     fn test_find_low_spots() {
-        assert_eq!(low_spots(), find_low_spots(&parsed_map()));
+        assert_eq!(low_spots(), find_low_spots(&parsed_map(), &PUZZLE_RULES));
+    }
+
+#[test]
+    fn test_risk_level() {
+        let map = parsed_map();
+        let risk : u32 = find_low_spots(&map, &PUZZLE_RULES).iter().map(|t| map[t.0][t.1] + 1).sum();
+        assert_eq!(risk_level(), risk);
     }
 
 #[test]
     fn test_measure_basin_size() {
+        let basin_map = label_basins(&parsed_map(), &PUZZLE_RULES);
         for low_spot in low_spots() {
-            assert_eq!(basin_size(low_spot), measure_basin(parsed_map(), low_spot)) 
+            assert_eq!(basin_size(low_spot), basin_map.basin_at(low_spot).unwrap().size())
         }
     }
+
+#[test]
+    fn test_label_basins() {
+        let basin_map = label_basins(&parsed_map(), &PUZZLE_RULES);
+        let low_points : Vec<Coords> = basin_map.basins.iter().map(|basin| basin.low_point).collect();
+        assert_eq!(low_points, low_spots());
+        assert_eq!(basin_map.basins[0].cells, vec![(1, 1), (1, 2), (2, 1)]);
+        assert_eq!(basin_map.basin_at((1, 3)), None);
+
+        let sizes : Vec<usize> = basin_map.largest_basins(3).iter().map(|basin| basin.size()).collect();
+        assert_eq!(sizes, vec![14, 9, 9]);
+    }
+
+#[test]
+    fn test_label_large_basin() {
+        let input = vec!["1".repeat(500); 500].join("\n");
+        let basin_map = label_basins(&make_input_map(&input), &PUZZLE_RULES);
+        assert_eq!(basin_map.basins.len(), 1);
+        assert_eq!(basin_map.basins[0].size(), 500 * 500);
+    }
+
+#[test]
+    fn test_eight_connectivity() {
+        let rules = BasinRules{connectivity: Connectivity::Eight, ..PUZZLE_RULES};
+        assert_eq!(find_low_spots(&parsed_map(), &rules), vec![(1, 2), (1, 10), (3, 3), (5, 7)]);
+
+        // diagonal gaps between the 9s join all four basins
+        let basin_map = label_basins(&parsed_map(), &rules);
+        let sizes : Vec<usize> = basin_map.basins.iter().map(|basin| basin.size()).collect();
+        assert_eq!(sizes, vec![3 + 14 + 9 + 9]);
+        assert_eq!(basin_map.basins[0].low_point, (1, 10));
+    }
+
+#[test]
+    fn test_wall_threshold() {
+        let rules = BasinRules{wall: 8, ..PUZZLE_RULES};
+        let basin_map = label_basins(&parsed_map(), &rules);
+        let sizes : Vec<usize> = basin_map.largest_basins(10).iter().map(|basin| basin.size()).collect();
+        assert_eq!(sizes, vec![9, 7, 6, 3]);
+
+        // without walls the whole map is one basin, but the padding still holds
+        let rules = BasinRules{wall: 10, ..PUZZLE_RULES};
+        let basin_map = label_basins(&parsed_map(), &rules);
+        assert_eq!(basin_map.basins.len(), 1);
+        assert_eq!(basin_map.basins[0].size(), 50);
+        assert_eq!(basin_map.basins[0].low_point, (1, 10));
+    }
+
+#[test]
+    fn test_plateaus() {
+        let map = make_input_map("1129\n2939\n9919\n");
+        assert_eq!(find_low_spots(&map, &PUZZLE_RULES), vec![(3, 3)]);
+
+        let rules = BasinRules{plateaus: true, ..PUZZLE_RULES};
+        assert_eq!(find_low_areas(&map, &rules), vec![vec![(1, 1), (1, 2)], vec![(3, 3)]]);
+        assert_eq!(find_low_spots(&map, &rules), vec![(1, 1), (3, 3)]);
+
+        // a plateau with a lower neighbour is only a shelf
+        let map = make_input_map("1102\n");
+        assert_eq!(find_low_areas(&map, &rules), vec![vec![(1, 3)]]);
+    }
+
+#[test]
+    fn test_basin_colours() {
+        let colours : HashSet<(u8, u8, u8)> = (0..20).map(|label| basin_colour(label, true)).collect();
+        assert_eq!(colours.len(), 20);
+        assert_eq!(hsv_to_rgb(0.0, 1.0, 1.0), (255, 0, 0));
+        assert_eq!(hsv_to_rgb(0.5, 1.0, 1.0), (0, 255, 255));
+        assert_eq!(hsv_to_rgb(0.25, 0.0, 0.5), (128, 128, 128));
+    }
+
+#[test]
+    fn test_render_basins() {
+        let map = parsed_map();
+        let basin_map = label_basins(&map, &PUZZLE_RULES);
+        let picture = BasinPicture::new(&map, &basin_map, &PUZZLE_RULES);
+
+        // the smallest basin is the only one left out of the highlight
+        assert_eq!(picture.colour_at((1, 1)), basin_colour(0, false));
+        assert_eq!(picture.colour_at((1, 9)), basin_colour(1, true));
+        assert_eq!(picture.colour_at((1, 3)), WALL_COLOUR);
+
+        let ppm = picture.render_ppm();
+        let mut lines = ppm.lines();
+        assert_eq!(lines.next(), Some("P3"));
+        assert_eq!(lines.next(), Some("10 5"));
+        assert_eq!(lines.next(), Some("255"));
+        let first_row : Vec<&str> = lines.next().unwrap().split(' ').collect();
+        assert_eq!(first_row.len(), 10 * 3);
+        assert_eq!(first_row[3..6], ["255", "255", "255"]);
+        assert_eq!(first_row[6..9], ["40", "40", "40"]);
+
+        let ansi = picture.render_ansi();
+        assert_eq!(ansi.lines().count(), 5);
+        let (r, g, b) = basin_colour(1, true);
+        let low_point = Style::new().fg(Colour::RGB(r, g, b)).bold().reverse().paint("0").to_string();
+        assert!(ansi.lines().next().unwrap().ends_with(&low_point));
+    }
 }
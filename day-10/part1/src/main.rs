@@ -1,51 +1,208 @@
+use std::env;
+use std::fmt;
 use std::fs;
-use std::collections::HashMap;
 
-type CodeLine = Vec<char>;
-type Code = Vec<CodeLine>;
+type Code = Vec<String>;
 
 fn parse_input(input: &str) -> Code {
-    input.split_whitespace().map(|line| line.chars().collect()).collect()
-}
-
-fn find_invalid_char(line: &CodeLine) -> Option<char> {
-    let mut stack = CodeLine::new();
-    let closing: HashMap<char, char> = HashMap::from([
-        ('(', ')'),
-        ('[', ']'),
-        ('{', '}'),
-        ('<', '>'),
-    ]);
-
-    for c in line {
-        match c {
-            '(' | '[' | '{' | '<' => stack.push(*c),
-            ')' | ']' | '}' | '>' => {
-                let p = stack.pop();
-                match p {
-                    None => break,
-                    Some(pp) => {
-                        if *c != closing[&pp] {
-                            return Some(*c);
+    input.lines().map(String::from).collect()
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Pair {
+    open: Vec<char>,
+    close: Vec<char>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Token {
+    Open(usize),
+    Close(usize),
+}
+
+const STANDARD_PAIRS : &str = "() [] {} <>";
+
+struct Checker {
+    pairs: Vec<Pair>,
+    // skip characters that are not part of any token instead of reporting them
+    ignore_others: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum ErrorKind {
+    Mismatched,
+    Unopened,
+    UnknownCharacter,
+}
+
+#[derive(Debug, PartialEq)]
+struct DelimiterError {
+    kind: ErrorKind,
+    line: usize,
+    column: usize,
+    found: String,
+    expected: Option<String>,
+}
+
+impl fmt::Display for DelimiterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match self.kind {
+            ErrorKind::UnknownCharacter => write!(f, "unknown character '{}'", self.found)?,
+            _ => write!(f, "unexpected '{}'", self.found)?,
+        }
+        match &self.expected {
+            Some(expected) => write!(f, ", expected '{}'", expected),
+            None => write!(f, ", nothing is open"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum LineStatus {
+    Complete,
+    // the closers that finish the line, innermost first
+    Incomplete(Vec<String>),
+    Corrupted(DelimiterError),
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+impl Checker {
+    // pairs separated by whitespace, each either two characters or open:close
+    fn parse(spec: &str, ignore_others: bool) -> Result<Checker, String> {
+        let mut pairs : Vec<Pair> = Vec::new();
+        for item in spec.split_whitespace() {
+            let (open, close) : (Vec<char>, Vec<char>) = match item.split_once(':') {
+                Some((open, close)) => (open.chars().collect(), close.chars().collect()),
+                None if item.chars().count() == 2 => (item.chars().take(1).collect(), item.chars().skip(1).collect()),
+                None => return Err(format!("pair '{}' is neither two characters nor open:close", item)),
+            };
+            if open.is_empty() || close.is_empty() {
+                return Err(format!("pair '{}' has an empty token", item));
+            }
+            pairs.push(Pair{open, close});
+        }
+        if pairs.is_empty() {
+            return Err("no delimiter pairs given".to_string());
+        }
+
+        let tokens : Vec<&Vec<char>> = pairs.iter().flat_map(|pair| [&pair.open, &pair.close]).collect();
+        for (i, token) in tokens.iter().enumerate() {
+            if tokens[..i].contains(token) {
+                return Err(format!("token '{}' is used more than once", token.iter().collect::<String>()));
+            }
+        }
+
+        Ok(Checker{pairs, ignore_others})
+    }
+
+    // the puzzle scores are only defined for these
+    fn has_standard_pairs(&self) -> bool {
+        let standard = Checker::parse(STANDARD_PAIRS, false).unwrap().pairs;
+        self.pairs.len() == standard.len() && standard.iter().all(|pair| self.pairs.contains(pair))
+    }
+
+    fn closer(&self, pair: usize) -> String {
+        self.pairs[pair].close.iter().collect()
+    }
+
+    // the longest token starting at position, and its length; word tokens like
+    // begin only match as whole words, so send or config don't hide an end or fi
+    fn token_at(&self, line: &[char], position: usize) -> Option<(Token, usize)> {
+        let rest = &line[position..];
+        let mut best : Option<(Token, usize)> = None;
+        for (i, pair) in self.pairs.iter().enumerate() {
+            for (token, text) in [(Token::Open(i), &pair.open), (Token::Close(i), &pair.close)] {
+                let end = position + text.len();
+                let starts_word = is_word_char(text[0]) && position > 0 && is_word_char(line[position - 1]);
+                let ends_word = is_word_char(text[text.len() - 1]) && end < line.len() && is_word_char(line[end]);
+                if rest.starts_with(text) && ! starts_word && ! ends_word && best.is_none_or(|(_, len)| text.len() > len) {
+                    best = Some((token, text.len()));
+                }
+            }
+        }
+        best
+    }
+
+    fn check(&self, line: &str, line_number: usize) -> LineStatus {
+        let line : Vec<char> = line.chars().collect();
+        let mut stack : Vec<usize> = Vec::new();
+        let mut position = 0;
+
+        while position < line.len() {
+            let error = |kind: ErrorKind, len: usize, stack: &[usize]| DelimiterError{
+                kind,
+                line: line_number,
+                column: position + 1,
+                found: line[position..position + len].iter().collect(),
+                expected: stack.last().map(|pair| self.closer(*pair)),
+            };
+            match self.token_at(&line, position) {
+                Some((Token::Open(pair), len)) => {
+                    stack.push(pair);
+                    position += len;
+                },
+                Some((Token::Close(pair), len)) => {
+                    match stack.last() {
+                        Some(open) if *open == pair => {
+                            stack.pop();
+                        },
+                        Some(_) => return LineStatus::Corrupted(error(ErrorKind::Mismatched, len, &stack)),
+                        None => return LineStatus::Corrupted(error(ErrorKind::Unopened, len, &stack)),
+                    }
+                    position += len;
+                },
+                None if self.ignore_others => {
+                    // step over a whole word, a token cannot start in the middle of one
+                    position += 1;
+                    if is_word_char(line[position - 1]) {
+                        while position < line.len() && is_word_char(line[position]) {
+                            position += 1;
                         }
                     }
-                }
+                },
+                None => return LineStatus::Corrupted(error(ErrorKind::UnknownCharacter, 1, &stack)),
             }
-            _ => panic!("Invalid character in input")
+        }
+
+        if stack.is_empty() {
+            LineStatus::Complete
+        } else {
+            LineStatus::Incomplete(stack.iter().rev().map(|pair| self.closer(*pair)).collect())
         }
     }
-    None
 }
 
-fn evaluate_invalid_chars(invalid_chars: & Vec<Option<char>>) -> u32 {
+fn get_option<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    let prefix = format!("--{}=", name);
+    args.iter().find_map(|a| a.strip_prefix(&prefix))
+}
+
+fn make_checker(args: &[String]) -> Checker {
+    let ignore_others = args.iter().any(|a| a == "--ignore-others");
+    Checker::parse(get_option(args, "pairs").unwrap_or(STANDARD_PAIRS), ignore_others).expect("invalid delimiter pairs")
+}
+
+fn find_invalid_char(checker: &Checker, line: &str, line_number: usize) -> Option<String> {
+    match checker.check(line, line_number) {
+        // a closer with nothing open is not scored, only one closing the wrong chunk
+        LineStatus::Corrupted(error) if error.kind == ErrorKind::Mismatched => Some(error.found),
+        _ => None,
+    }
+}
+
+fn evaluate_invalid_chars(invalid_chars: &[Option<String>]) -> u32 {
     let mut result: u32 = 0;
     for c in invalid_chars {
-        match c {
+        match c.as_deref() {
             None => continue,
-            Some(')') => result += 3,
-            Some(']') => result += 57,
-            Some('}') => result += 1197,
-            Some('>') => result += 25137,
+            Some(")") => result += 3,
+            Some("]") => result += 57,
+            Some("}") => result += 1197,
+            Some(">") => result += 25137,
             _ => panic!("invalid char for evaluation")
         }
     }
@@ -55,7 +212,24 @@ fn evaluate_invalid_chars(invalid_chars: & Vec<Option<char>>) -> u32 {
 fn main() {
     let input = fs::read_to_string("input.txt").expect("failed to load input file");
     let lines = parse_input(&input);
-    let invalid_chars : Vec<Option<char>> = lines.iter().map(|l| find_invalid_char(l)).collect();
+    let args : Vec<String> = env::args().skip(1).collect();
+    let checker = make_checker(&args);
+
+    if args.iter().any(|a| a == "--report") {
+        for (i, line) in lines.iter().enumerate() {
+            if let LineStatus::Corrupted(error) = checker.check(line, i + 1) {
+                println!("{}", error);
+            }
+        }
+        return;
+    }
+
+    if ! checker.has_standard_pairs() {
+        eprintln!("scores are only defined for the pairs '{}', use --report with other pairs", STANDARD_PAIRS);
+        return;
+    }
+
+    let invalid_chars : Vec<Option<String>> = lines.iter().enumerate().map(|(i, l)| find_invalid_char(&checker, l, i + 1)).collect();
     let evaluation = evaluate_invalid_chars(&invalid_chars);
     println!("{}", evaluation);
 }
@@ -82,30 +256,30 @@ mod tests {
 
     fn parsed_input() -> Code {
         vec![
-            vec!['[','(','{','(','<','(','(',')',')','[',']','>','[','[','{','[',']','{','<','(',')','<','>','>'],
-            vec!['[','(','(',')','[','<','>',']',')',']','(','{','[','<','{','<','<','[',']','>','>','('],
-            vec!['{','(','[','(','<','{','}','[','<','>','[',']','}','>','{','[',']','{','[','(','<','(',')','>'],
-            vec!['(','(','(','(','{','<','>','}','<','{','<','{','<','>','}','{','[',']','{','[',']','{','}'],
-            vec!['[','[','<','[','(','[',']',')',')','<','(','[','[','{','}','[','[','(',')',']',']',']'],
-            vec!['[','{','[','{','(','{','}',']','{','}','}','(','[','{','[','{','{','{','}','}','(','[',']'],
-            vec!['{','<','[','[',']',']','>','}','<','{','[','{','[','{','[',']','{','(',')','[','[','[',']'],
-            vec!['[','<','(','<','(','<','(','<','{','}',')',')','>','<','(','[',']','(','[',']','(',')'],
-            vec!['<','{','(','[','(','[','[','(','<','>','(',')',')','{','}',']','>','(','<','<','{','{'],
-            vec!['<','{','(','[','{','{','}','}','[','<','[','[','[','<','>','{','}',']',']',']','>','[',']',']'],
+            "[({(<(())[]>[[{[]{<()<>>".to_string(),
+            "[(()[<>])]({[<{<<[]>>(".to_string(),
+            "{([(<{}[<>[]}>{[]{[(<()>".to_string(),
+            "(((({<>}<{<{<>}{[]{[]{}".to_string(),
+            "[[<[([]))<([[{}[[()]]]".to_string(),
+            "[{[{({}]{}}([{[{{{}}([]".to_string(),
+            "{<[[]]>}<{[{[{[]{()[[[]".to_string(),
+            "[<(<(<(<{}))><([]([]()".to_string(),
+            "<{([([[(<>()){}]>(<<{{".to_string(),
+            "<{([{{}}[<[[[<>{}]]]>[]]".to_string(),
         ]
     }
 
-    fn invalid_chars() -> Vec<Option<char>> {
+    fn invalid_chars() -> Vec<Option<String>> {
         vec![
             None,
             None,
-            Some('}'),
+            Some("}".to_string()),
             None,
-            Some(')'),
-            Some(']'),
+            Some(")".to_string()),
+            Some("]".to_string()),
             None,
-            Some(')'),
-            Some('>'),
+            Some(")".to_string()),
+            Some(">".to_string()),
             None,
         ]
     }
@@ -121,13 +295,73 @@ mod tests {
 
 #[test]
     fn test_find_invalid_character() {
-        for (input, invalid) in parsed_input().iter().zip(invalid_chars().iter()) {
-            assert_eq!(find_invalid_char(input), *invalid);
+        let checker = Checker::parse(STANDARD_PAIRS, false).unwrap();
+        for (i, (input, invalid)) in parsed_input().iter().zip(invalid_chars().iter()).enumerate() {
+            assert_eq!(find_invalid_char(&checker, input, i + 1), *invalid);
         }
+        assert_eq!(find_invalid_char(&checker, "())", 1), None);
+        assert_eq!(find_invalid_char(&checker, "(x]", 1), None);
+        assert_eq!(find_invalid_char(&checker, "(<]", 1), Some("]".to_string()));
     }
 
 #[test]
     fn test_evaluate_invalid_chars() {
         assert_eq!(evaluate_invalid_chars(&invalid_chars()), evaluation());
     }
+
+#[test]
+    fn test_error_positions() {
+        let checker = Checker::parse(STANDARD_PAIRS, false).unwrap();
+        let line = &parsed_input()[2];
+        let error = DelimiterError{kind: ErrorKind::Mismatched, line: 3, column: 13, found: "}".to_string(), expected: Some("]".to_string())};
+        assert_eq!(error.to_string(), "line 3, column 13: unexpected '}', expected ']'");
+        assert_eq!(checker.check(line, 3), LineStatus::Corrupted(error));
+
+        match checker.check("()>", 7) {
+            LineStatus::Corrupted(error) => assert_eq!(error.to_string(), "line 7, column 3: unexpected '>', nothing is open"),
+            status => panic!("unexpected {:?}", status),
+        }
+        match checker.check("(a)", 1) {
+            LineStatus::Corrupted(error) => assert_eq!(error.to_string(), "line 1, column 2: unknown character 'a', expected ')'"),
+            status => panic!("unexpected {:?}", status),
+        }
+
+        let checker = Checker::parse(STANDARD_PAIRS, true).unwrap();
+        assert_eq!(checker.check("(a + [b])", 1), LineStatus::Complete);
+        assert_eq!(checker.check("f(x[0", 1), LineStatus::Incomplete(vec!["]".to_string(), ")".to_string()]));
+    }
+
+#[test]
+    fn test_word_pairs() {
+        let checker = Checker::parse("begin:end () if:fi", true).unwrap();
+        assert_eq!(checker.check("begin if (x) fi end", 1), LineStatus::Complete);
+        assert_eq!(checker.check("begin if x end fi", 4), LineStatus::Corrupted(DelimiterError{
+            kind: ErrorKind::Mismatched, line: 4, column: 12, found: "end".to_string(), expected: Some("fi".to_string()),
+        }));
+        assert_eq!(checker.check("begin begin (", 1), LineStatus::Incomplete(vec![")".to_string(), "end".to_string(), "end".to_string()]));
+
+        // word tokens only count as whole words
+        assert_eq!(checker.check("begin send(x) end", 1), LineStatus::Complete);
+        assert_eq!(checker.check("config(y)", 1), LineStatus::Complete);
+        assert_eq!(checker.check("begin_loop endif (fifo)", 1), LineStatus::Complete);
+        assert_eq!(checker.check("(begin) end", 1), LineStatus::Corrupted(DelimiterError{
+            kind: ErrorKind::Mismatched, line: 1, column: 7, found: ")".to_string(), expected: Some("end".to_string()),
+        }));
+        assert_eq!(checker.check("if x fi", 1), LineStatus::Complete);
+
+        // the longest token wins where one is a prefix of another
+        let checker = Checker::parse("<:> <<:>>", false).unwrap();
+        assert_eq!(checker.check("<<<>", 1), LineStatus::Incomplete(vec![">>".to_string()]));
+    }
+
+#[test]
+    fn test_parse_pairs() {
+        assert!(Checker::parse("", false).is_err());
+        assert!(Checker::parse("(", false).is_err());
+        assert!(Checker::parse("begin:", false).is_err());
+        assert_eq!(Checker::parse("() (]", false).err().unwrap(), "token '(' is used more than once");
+        assert!(Checker::parse("<> () [] {}", true).unwrap().has_standard_pairs());
+        assert!(! Checker::parse("begin:end ()", true).unwrap().has_standard_pairs());
+        assert_eq!(Checker::parse("begin:end", false).unwrap().pairs, vec![Pair{open: "begin".chars().collect(), close: "end".chars().collect()}]);
+    }
 }
@@ -1,54 +1,210 @@
+use std::env;
+use std::fmt;
 use std::fs;
 use std::collections::HashMap;
 
-type CodeLine = Vec<char>;
-type Code = Vec<CodeLine>;
+type Code = Vec<String>;
 
 fn parse_input(input: &str) -> Code {
-    input.split_whitespace().map(|line| line.chars().collect()).collect()
+    input.lines().map(String::from).collect()
 }
 
-fn find_missing_chars(line: &CodeLine) -> CodeLine {
-    let mut stack = CodeLine::new();
-    let closing: HashMap<char, char> = HashMap::from([
-        ('(', ')'),
-        ('[', ']'),
-        ('{', '}'),
-        ('<', '>'),
-    ]);
+#[derive(Debug, PartialEq, Clone)]
+struct Pair {
+    open: Vec<char>,
+    close: Vec<char>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Token {
+    Open(usize),
+    Close(usize),
+}
+
+const STANDARD_PAIRS : &str = "() [] {} <>";
+
+struct Checker {
+    pairs: Vec<Pair>,
+    // skip characters that are not part of any token instead of reporting them
+    ignore_others: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum ErrorKind {
+    Mismatched,
+    Unopened,
+    UnknownCharacter,
+}
+
+#[derive(Debug, PartialEq)]
+struct DelimiterError {
+    kind: ErrorKind,
+    line: usize,
+    column: usize,
+    found: String,
+    expected: Option<String>,
+}
+
+impl fmt::Display for DelimiterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match self.kind {
+            ErrorKind::UnknownCharacter => write!(f, "unknown character '{}'", self.found)?,
+            _ => write!(f, "unexpected '{}'", self.found)?,
+        }
+        match &self.expected {
+            Some(expected) => write!(f, ", expected '{}'", expected),
+            None => write!(f, ", nothing is open"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum LineStatus {
+    Complete,
+    // the closers that finish the line, innermost first
+    Incomplete(Vec<String>),
+    Corrupted(DelimiterError),
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+impl Checker {
+    // pairs separated by whitespace, each either two characters or open:close
+    fn parse(spec: &str, ignore_others: bool) -> Result<Checker, String> {
+        let mut pairs : Vec<Pair> = Vec::new();
+        for item in spec.split_whitespace() {
+            let (open, close) : (Vec<char>, Vec<char>) = match item.split_once(':') {
+                Some((open, close)) => (open.chars().collect(), close.chars().collect()),
+                None if item.chars().count() == 2 => (item.chars().take(1).collect(), item.chars().skip(1).collect()),
+                None => return Err(format!("pair '{}' is neither two characters nor open:close", item)),
+            };
+            if open.is_empty() || close.is_empty() {
+                return Err(format!("pair '{}' has an empty token", item));
+            }
+            pairs.push(Pair{open, close});
+        }
+        if pairs.is_empty() {
+            return Err("no delimiter pairs given".to_string());
+        }
+
+        let tokens : Vec<&Vec<char>> = pairs.iter().flat_map(|pair| [&pair.open, &pair.close]).collect();
+        for (i, token) in tokens.iter().enumerate() {
+            if tokens[..i].contains(token) {
+                return Err(format!("token '{}' is used more than once", token.iter().collect::<String>()));
+            }
+        }
+
+        Ok(Checker{pairs, ignore_others})
+    }
+
+    // the puzzle scores are only defined for these
+    fn has_standard_pairs(&self) -> bool {
+        let standard = Checker::parse(STANDARD_PAIRS, false).unwrap().pairs;
+        self.pairs.len() == standard.len() && standard.iter().all(|pair| self.pairs.contains(pair))
+    }
+
+    fn closer(&self, pair: usize) -> String {
+        self.pairs[pair].close.iter().collect()
+    }
+
+    // the longest token starting at position, and its length; word tokens like
+    // begin only match as whole words, so send or config don't hide an end or fi
+    fn token_at(&self, line: &[char], position: usize) -> Option<(Token, usize)> {
+        let rest = &line[position..];
+        let mut best : Option<(Token, usize)> = None;
+        for (i, pair) in self.pairs.iter().enumerate() {
+            for (token, text) in [(Token::Open(i), &pair.open), (Token::Close(i), &pair.close)] {
+                let end = position + text.len();
+                let starts_word = is_word_char(text[0]) && position > 0 && is_word_char(line[position - 1]);
+                let ends_word = is_word_char(text[text.len() - 1]) && end < line.len() && is_word_char(line[end]);
+                if rest.starts_with(text) && ! starts_word && ! ends_word && best.is_none_or(|(_, len)| text.len() > len) {
+                    best = Some((token, text.len()));
+                }
+            }
+        }
+        best
+    }
 
-    for c in line {
-        match c {
-            '(' | '[' | '{' | '<' => stack.push(*c),
-            ')' | ']' | '}' | '>' => {
-                let p = stack.pop();
-                match p {
-                    None => break,
-                    Some(pp) => {
-                        if *c != closing[&pp] {
-                            return vec![];
+    fn check(&self, line: &str, line_number: usize) -> LineStatus {
+        let line : Vec<char> = line.chars().collect();
+        let mut stack : Vec<usize> = Vec::new();
+        let mut position = 0;
+
+        while position < line.len() {
+            let error = |kind: ErrorKind, len: usize, stack: &[usize]| DelimiterError{
+                kind,
+                line: line_number,
+                column: position + 1,
+                found: line[position..position + len].iter().collect(),
+                expected: stack.last().map(|pair| self.closer(*pair)),
+            };
+            match self.token_at(&line, position) {
+                Some((Token::Open(pair), len)) => {
+                    stack.push(pair);
+                    position += len;
+                },
+                Some((Token::Close(pair), len)) => {
+                    match stack.last() {
+                        Some(open) if *open == pair => {
+                            stack.pop();
+                        },
+                        Some(_) => return LineStatus::Corrupted(error(ErrorKind::Mismatched, len, &stack)),
+                        None => return LineStatus::Corrupted(error(ErrorKind::Unopened, len, &stack)),
+                    }
+                    position += len;
+                },
+                None if self.ignore_others => {
+                    // step over a whole word, a token cannot start in the middle of one
+                    position += 1;
+                    if is_word_char(line[position - 1]) {
+                        while position < line.len() && is_word_char(line[position]) {
+                            position += 1;
                         }
                     }
-                }
+                },
+                None => return LineStatus::Corrupted(error(ErrorKind::UnknownCharacter, 1, &stack)),
             }
-            _ => panic!("Invalid character in input")
         }
+
+        if stack.is_empty() {
+            LineStatus::Complete
+        } else {
+            LineStatus::Incomplete(stack.iter().rev().map(|pair| self.closer(*pair)).collect())
+        }
+    }
+}
+
+fn get_option<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    let prefix = format!("--{}=", name);
+    args.iter().find_map(|a| a.strip_prefix(&prefix))
+}
+
+fn make_checker(args: &[String]) -> Checker {
+    let ignore_others = args.iter().any(|a| a == "--ignore-others");
+    Checker::parse(get_option(args, "pairs").unwrap_or(STANDARD_PAIRS), ignore_others).expect("invalid delimiter pairs")
+}
+
+fn find_missing_chars(checker: &Checker, line: &str, line_number: usize) -> Vec<String> {
+    match checker.check(line, line_number) {
+        LineStatus::Incomplete(missing) => missing,
+        _ => vec![],
     }
-    stack.reverse();
-    stack.iter().map(|c| closing[c]).collect()
 }
 
-fn evaluate_line_of_missing(line: & CodeLine) -> u64 {
-    let values = HashMap::<char, u64>::from([
-        (')', 1),
-        (']', 2),
-        ('}', 3),
-        ('>', 4),
+fn evaluate_line_of_missing(line: &[String]) -> u64 {
+    let values = HashMap::<&str, u64>::from([
+        (")", 1),
+        ("]", 2),
+        ("}", 3),
+        (">", 4),
 
     ]);
 
     let mut line_total : u64 = 0;
-    for v in line.iter().map(|c| values[c]) {
+    for v in line.iter().map(|c| values[c.as_str()]) {
         line_total *= 5;
         line_total += v;
     }
@@ -61,16 +217,137 @@ fn median(mut data: Vec<u64>) -> u64 {
     data[data.len()/2]
 }
 
-fn evaluate_lines_of_missing(lines: & Code) -> u64 {
-    let mut totals : Vec<u64> = lines.iter().map(|line| evaluate_line_of_missing(&line)).collect();
-    totals = totals.iter().filter(|v| **v > 0).map(|v| *v).collect();
+fn evaluate_lines_of_missing(lines: &[Vec<String>]) -> u64 {
+    let totals : Vec<u64> = lines.iter().map(|line| evaluate_line_of_missing(line)).filter(|v| *v > 0).collect();
     median(totals)
 }
 
+#[derive(Debug, PartialEq, Clone)]
+enum Edit {
+    Replace { column: usize, found: char, with: String },
+    Delete { column: usize, found: char },
+}
+
+impl fmt::Display for Edit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Edit::Replace{column, found, with} => write!(f, "replace '{}' at column {} with '{}'", found, column, with),
+            Edit::Delete{column, found} => write!(f, "delete '{}' at column {}", found, column),
+        }
+    }
+}
+
+fn apply_edit(line: &str, edit: &Edit) -> String {
+    let mut chars : Vec<String> = line.chars().map(String::from).collect();
+    match edit {
+        Edit::Replace{column, with, ..} => chars[column - 1] = with.clone(),
+        Edit::Delete{column, ..} => { chars.remove(column - 1); },
+    }
+    chars.concat()
+}
+
+// every single character edit up to the end of the offending token that leaves the line
+// uncorrupted; the best one needs the shortest completion and sits closest to the error
+fn find_minimal_edit(checker: &Checker, line: &str, error: &DelimiterError) -> Option<Edit> {
+    let chars : Vec<char> = line.chars().collect();
+    let error_start = error.column - 1;
+    let error_end = (error_start + error.found.chars().count()).min(chars.len());
+    let tokens : Vec<String> = checker.pairs.iter()
+        .flat_map(|pair| [pair.open.iter().collect(), pair.close.iter().collect()])
+        .collect();
+
+    let mut best : Option<((usize, usize, usize), Edit)> = None;
+    for (position, found) in chars.iter().copied().enumerate().take(error_end) {
+        let column = position + 1;
+        let mut edits = vec![Edit::Delete{column, found}];
+        edits.extend(tokens.iter().filter(|token| **token != found.to_string()).map(|token| Edit::Replace{column, found, with: token.clone()}));
+
+        for (rank, edit) in edits.into_iter().enumerate() {
+            let completion = match checker.check(&apply_edit(line, &edit), error.line) {
+                LineStatus::Complete => 0,
+                LineStatus::Incomplete(missing) => missing.len(),
+                LineStatus::Corrupted(_) => continue,
+            };
+            let key = (completion, error_start.abs_diff(position), rank);
+            if best.as_ref().is_none_or(|(best_key, _)| key < *best_key) {
+                best = Some((key, edit));
+            }
+        }
+    }
+
+    best.map(|(_, edit)| edit)
+}
+
+#[derive(Debug, PartialEq)]
+struct Repair {
+    text: String,
+    edit: Option<Edit>,
+    completion: Vec<String>,
+}
+
+// a line with its corruption fixed, if there is any, and then completed
+fn repair_line(checker: &Checker, line: &str, line_number: usize) -> Result<Repair, DelimiterError> {
+    let (mut text, edit) = match checker.check(line, line_number) {
+        LineStatus::Corrupted(error) => match find_minimal_edit(checker, line, &error) {
+            Some(edit) => (apply_edit(line, &edit), Some(edit)),
+            None => return Err(error),
+        },
+        _ => (line.to_string(), None),
+    };
+    let completion = find_missing_chars(checker, &text, line_number);
+    text.push_str(&completion.concat());
+
+    Ok(Repair{text, edit, completion})
+}
+
 fn main() {
     let input = fs::read_to_string("input.txt").expect("failed to load input file");
     let lines = parse_input(&input);
-    let missing = lines.iter().map(|line| find_missing_chars(&line)).collect();
+    let args : Vec<String> = env::args().skip(1).collect();
+    let checker = make_checker(&args);
+
+    if args.iter().any(|a| a == "--report") {
+        for (i, line) in lines.iter().enumerate() {
+            match checker.check(line, i + 1) {
+                LineStatus::Corrupted(error) => println!("{}", error),
+                LineStatus::Incomplete(missing) => println!("line {}: incomplete, missing '{}'", i + 1, missing.concat()),
+                LineStatus::Complete => {},
+            }
+        }
+        return;
+    }
+
+    if let Some(path) = get_option(&args, "repair") {
+        let mut repaired : Vec<String> = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            match repair_line(&checker, line, i + 1) {
+                Ok(repair) => {
+                    let mut changes : Vec<String> = repair.edit.iter().map(|edit| edit.to_string()).collect();
+                    if ! repair.completion.is_empty() {
+                        changes.push(format!("complete with '{}'", repair.completion.concat()));
+                    }
+                    if ! changes.is_empty() {
+                        println!("line {}: {}", i + 1, changes.join(", "));
+                    }
+                    repaired.push(repair.text);
+                },
+                Err(error) => {
+                    println!("{}, no single character edit repairs it", error);
+                    repaired.push(line.clone());
+                },
+            }
+        }
+        repaired.push(String::new());
+        fs::write(path, repaired.join("\n")).expect("failed to write repaired file");
+        return;
+    }
+
+    if ! checker.has_standard_pairs() {
+        eprintln!("scores are only defined for the pairs '{}', use --report or --repair with other pairs", STANDARD_PAIRS);
+        return;
+    }
+
+    let missing : Vec<Vec<String>> = lines.iter().enumerate().map(|(i, line)| find_missing_chars(&checker, line, i + 1)).collect();
     println!("{}", evaluate_lines_of_missing(&missing));
 }
 
@@ -96,31 +373,35 @@ mod tests {
 
     fn parsed_input() -> Code {
         vec![
-            vec!['[','(','{','(','<','(','(',')',')','[',']','>','[','[','{','[',']','{','<','(',')','<','>','>'],
-            vec!['[','(','(',')','[','<','>',']',')',']','(','{','[','<','{','<','<','[',']','>','>','('],
-            vec!['{','(','[','(','<','{','}','[','<','>','[',']','}','>','{','[',']','{','[','(','<','(',')','>'],
-            vec!['(','(','(','(','{','<','>','}','<','{','<','{','<','>','}','{','[',']','{','[',']','{','}'],
-            vec!['[','[','<','[','(','[',']',')',')','<','(','[','[','{','}','[','[','(',')',']',']',']'],
-            vec!['[','{','[','{','(','{','}',']','{','}','}','(','[','{','[','{','{','{','}','}','(','[',']'],
-            vec!['{','<','[','[',']',']','>','}','<','{','[','{','[','{','[',']','{','(',')','[','[','[',']'],
-            vec!['[','<','(','<','(','<','(','<','{','}',')',')','>','<','(','[',']','(','[',']','(',')'],
-            vec!['<','{','(','[','(','[','[','(','<','>','(',')',')','{','}',']','>','(','<','<','{','{'],
-            vec!['<','{','(','[','{','{','}','}','[','<','[','[','[','<','>','{','}',']',']',']','>','[',']',']'],
+            "[({(<(())[]>[[{[]{<()<>>".to_string(),
+            "[(()[<>])]({[<{<<[]>>(".to_string(),
+            "{([(<{}[<>[]}>{[]{[(<()>".to_string(),
+            "(((({<>}<{<{<>}{[]{[]{}".to_string(),
+            "[[<[([]))<([[{}[[()]]]".to_string(),
+            "[{[{({}]{}}([{[{{{}}([]".to_string(),
+            "{<[[]]>}<{[{[{[]{()[[[]".to_string(),
+            "[<(<(<(<{}))><([]([]()".to_string(),
+            "<{([([[(<>()){}]>(<<{{".to_string(),
+            "<{([{{}}[<[[[<>{}]]]>[]]".to_string(),
         ]
     }
 
-    fn missing_chars() -> Vec<Vec<char>> {
+    fn tokens(text: &str) -> Vec<String> {
+        text.chars().map(String::from).collect()
+    }
+
+    fn missing_chars() -> Vec<Vec<String>> {
         vec![
-            vec!['}', '}', ']', ']', ')', '}', ')', ']'],
-            vec![')', '}', '>', ']', '}', ')'],
+            tokens("}}]])})]"),
+            tokens(")}>]})"),
             vec![],
-            vec!['}', '}', '>', '}', '>', ')', ')', ')', ')'],
+            tokens("}}>}>))))"),
             vec![],
             vec![],
-            vec![']', ']', '}', '}', ']', '}', ']', '}', '>'],
+            tokens("]]}}]}]}>"),
             vec![],
             vec![],
-            vec![']', ')', '}', '>'],
+            tokens("])}>"),
         ]
     }
 
@@ -131,14 +412,15 @@ mod tests {
 
 #[test]
     fn test_find_incomplete_lines() {
-        for (input, missing) in parsed_input().iter().zip(missing_chars().iter()) {
-            assert_eq!(find_missing_chars(input), *missing);
+        let checker = Checker::parse(STANDARD_PAIRS, false).unwrap();
+        for (i, (input, missing)) in parsed_input().iter().zip(missing_chars().iter()).enumerate() {
+            assert_eq!(find_missing_chars(&checker, input, i + 1), *missing);
         }
     }
 
 #[test]
     fn test_evaluate_line_of_missing() {
-        assert_eq!(evaluate_line_of_missing(&vec!['}', '}', ']', ']', ')', '}', ')', ']']), 288957);
+        assert_eq!(evaluate_line_of_missing(&tokens("}}]])})]")), 288957);
     }
 
 #[test]
@@ -151,4 +433,52 @@ mod tests {
         assert_eq!(evaluate_lines_of_missing(&missing_chars()), 288957);
     }
 
+#[test]
+    fn test_complete_custom_pairs() {
+        let checker = Checker::parse("begin:end () if:fi", true).unwrap();
+        assert_eq!(find_missing_chars(&checker, "begin if (x", 1), vec![")".to_string(), "fi".to_string(), "end".to_string()]);
+        assert_eq!(find_missing_chars(&checker, "begin x fi", 1), Vec::<String>::new());
+        assert!(! checker.has_standard_pairs());
+        assert!(Checker::parse("<> () [] {}", true).unwrap().has_standard_pairs());
+    }
+
+#[test]
+    fn test_minimal_edit() {
+        let checker = Checker::parse(STANDARD_PAIRS, false).unwrap();
+        let repair = |line: &str| repair_line(&checker, line, 1).unwrap();
+
+        assert_eq!(repair("(]"), Repair{text: "()".to_string(), edit: Some(Edit::Replace{column: 2, found: ']', with: ")".to_string()}), completion: vec![]});
+        assert_eq!(repair("[(])"), Repair{text: "[()]".to_string(), edit: Some(Edit::Delete{column: 3, found: ']'}), completion: tokens("]")});
+        assert_eq!(repair("[(").text, "[()]");
+        assert_eq!(repair("()").edit, None);
+        assert_eq!(Edit::Delete{column: 3, found: ']'}.to_string(), "delete ']' at column 3");
+
+        let checker = Checker::parse("() if:fi", true).unwrap();
+        let repair = repair_line(&checker, "if (x fi", 1).unwrap();
+        assert_eq!(repair.text, "if (x)fi");
+        assert_eq!(repair.edit, Some(Edit::Replace{column: 6, found: ' ', with: ")".to_string()}));
+
+        // words that merely contain a token are left alone
+        let checker = Checker::parse("begin:end () if:fi", true).unwrap();
+        let repair = repair_line(&checker, "begin send(x) end", 1).unwrap();
+        assert_eq!(repair, Repair{text: "begin send(x) end".to_string(), edit: None, completion: vec![]});
+        let repair = repair_line(&checker, "if config(y) fi", 1).unwrap();
+        assert_eq!(repair, Repair{text: "if config(y) fi".to_string(), edit: None, completion: vec![]});
+        let repair = repair_line(&checker, "begin send(x end", 1).unwrap();
+        assert_eq!(repair.text, "begin send(x)end");
+        assert_eq!(repair.edit, Some(Edit::Replace{column: 13, found: ' ', with: ")".to_string()}));
+    }
+
+#[test]
+    fn test_repaired_lines_check_complete() {
+        let checker = Checker::parse(STANDARD_PAIRS, false).unwrap();
+        for (i, line) in parsed_input().iter().enumerate() {
+            let repair = repair_line(&checker, line, i + 1).unwrap();
+            assert_eq!(checker.check(&repair.text, i + 1), LineStatus::Complete);
+            assert_eq!(repair.edit.is_some(), find_missing_chars(&checker, line, i + 1).is_empty());
+        }
+
+        // two mistakes are more than one edit can fix
+        assert!(repair_line(&checker, "]]]", 1).is_err());
+    }
 }